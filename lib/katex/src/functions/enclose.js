@@ -117,6 +117,11 @@ const htmlBuilder = (group, options) => {
                 img.style.borderColor = group.borderColor;
             }
         }
+
+        // The cancel lines are drawn with the current color.
+        if (/cancel/.test(label) && group.borderColor) {
+            img.style.color = group.borderColor;
+        }
     }
 
     let vlist;
@@ -28,6 +28,8 @@ const stretchyCodePoint                     = {
     xrightarrow: "\u2192",
     underbrace: "\u23df",
     overbrace: "\u23de",
+    underbracket: "\u23b5",
+    overbracket: "\u23b4",
     overgroup: "\u23e0",
     undergroup: "\u23e1",
     overleftrightarrow: "\u2194",
@@ -145,6 +147,8 @@ const katexImagesData
     overbrace: [["leftbrace", "midbrace", "rightbrace"], 1.6, 548],
     underbrace: [["leftbraceunder", "midbraceunder", "rightbraceunder"],
         1.6, 548],
+    overbracket: [["leftbracket", "rightbracket"], 0.888, 300],
+    underbracket: [["leftbracketunder", "rightbracketunder"], 0.888, 300],
     underleftrightarrow: [["leftarrow", "rightarrow"], 0.888, 522],
     xleftrightarrow: [["leftarrow", "rightarrow"], 1.75, 522],
     xLeftrightarrow: [["doubleleftarrow", "doublerightarrow"], 1.75, 560],
@@ -220,6 +220,10 @@ c-10 4-16 7.7-18 11 0 8.7 6 14.3 18 17 47.3 18.7 87.8 47 121.5 85S196 441.3 208
  0 2 .3 4 1 18.688 2.7 76 4.3 172 5h399450v120H429l-6-1c-124.688-8-235-61.7
 -331-161C60.687 138.7 32.312 99.3 7 54L0 41V6z`,
 
+    leftbracket: `M0 300V0H400000v40H40v260z`,
+
+    leftbracketunder: `M0 0V300H400000v-40H40V0z`,
+
     // overgroup is from the MnSymbol package (public domain)
     leftgroup: `M400000 80
 H435C64 80 168.3 229.4 21 260c-5.9 1.2-18 0-18 0-2 0-3-1-3-3v-38C76 61 257 0
@@ -321,6 +325,10 @@ c124.7 8 235 61.7 331 161 31.3 33.3 59.7 72.7 85 118l7 13v35z`,
  28.7-117 45-179 50H-300V214h399897c43.3-7 81-15 113-26 100.7-33 179.7-91 237
 -174 2.7-5 6-9 10-13 .7-1 7.3-1 20-1h17z`,
 
+    rightbracket: `M400000 300V0H0v40h399960v260z`,
+
+    rightbracketunder: `M400000 0V300H0v-40h399960V0z`,
+
     rightgroup: `M0 80h399565c371 0 266.7 149.4 414 180 5.9 1.2 18 0 18 0 2 0
  3-1 3-3v-38c-76-158-257-219-435-219H0z`,
 
@@ -1,38 +1,43 @@
 use crate::node::*;
+use crate::error::*;
 use typst;
 use typst::foundations::Content;
 
 pub trait ContentVisitor {
-    fn visit_equation(&mut self, content: &Content) -> Node;
-    fn visit_sequence(&mut self, content: &Content) -> Node;
-    fn visit_text(&mut self, content: &Content) -> Node;
-    fn visit_space(&mut self, content: &Content) -> Node;
-    fn visit_lr(&mut self, content: &Content) -> Node;
-    fn visit_attach(&mut self, content: &Content) -> Node;
-    fn visit_math_style(&mut self, content: &Content) -> Node;
-    fn visit_h(&mut self, content: &Content) -> Node;
-    fn visit_linebreak(&mut self, content: &Content) -> Node;
-    fn visit_align_point(&mut self, content: &Content) -> Node;
-    fn visit_frac(&mut self, content: &Content) -> Node;
-    fn visit_vec(&mut self, content: &Content) -> Node;
-    fn visit_mat(&mut self, content: &Content) -> Node;
-    fn visit_op(&mut self, content: &Content) -> Node;
-    fn visit_cases(&mut self, content: &Content) -> Node;
-    fn visit_overbracket(&mut self, content: &Content) -> Node;
-    fn visit_underbracket(&mut self, content: &Content) -> Node;
-    fn visit_overbrace(&mut self, content: &Content) -> Node;
-    fn visit_underbrace(&mut self, content: &Content) -> Node;
-    fn visit_overline(&mut self, content: &Content) -> Node;
-    fn visit_underline(&mut self, content: &Content) -> Node;
-    fn visit_root(&mut self, content: &Content) -> Node;
-    fn visit_mid(&mut self, content: &Content) -> Node;
-    fn visit_binom(&mut self, content: &Content) -> Node;
-    fn visit_class(&mut self, content: &Content) -> Node;
-    fn visit_cancel(&mut self, content: &Content) -> Node;
-    fn visit_limits(&mut self, content: &Content) -> Node;
-    fn visit_scripts(&mut self, content: &Content) -> Node;
-    fn visit_primes(&mut self, content: &Content) -> Node;
-    fn visit_accent(&mut self, content: &Content) -> Node;
+    fn enter(&mut self, content: &Content) -> ConvertResult<()>;
+    fn leave(&mut self, content: &Content);
+    fn locate(&self, content: &Content, node: Node) -> Node;
+    fn visit_equation(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_sequence(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_text(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_space(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_lr(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_attach(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_math_style(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_styled(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_h(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_linebreak(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_align_point(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_frac(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_vec(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_mat(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_op(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_cases(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_overbracket(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_underbracket(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_overbrace(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_underbrace(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_overline(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_underline(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_root(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_mid(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_binom(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_class(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_cancel(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_limits(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_scripts(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_primes(&mut self, content: &Content) -> ConvertResult<Node>;
+    fn visit_accent(&mut self, content: &Content) -> ConvertResult<Node>;
 }
 
 pub trait ContentType {
@@ -276,18 +281,20 @@ impl ContentType for Content {
 }
 
 pub trait ContentExt {
-    fn accept(&self, visitor: &mut dyn ContentVisitor) -> Node;
+    fn accept(&self, visitor: &mut dyn ContentVisitor) -> ConvertResult<Node>;
 }
 
 impl ContentExt for Content {
-    fn accept(&self, visitor: &mut dyn ContentVisitor) -> Node {
-        match self {
+    fn accept(&self, visitor: &mut dyn ContentVisitor) -> ConvertResult<Node> {
+        visitor.enter(self)?;
+        let node = match self {
             _ if self.is_equation() => visitor.visit_equation(self),
             _ if self.is_space() => visitor.visit_space(self),
             _ if self.is_text() => visitor.visit_text(self),
             _ if self.is_lr() => visitor.visit_lr(self),
             _ if self.is_attach() => visitor.visit_attach(self),
             _ if self.is_math_style() => visitor.visit_math_style(self),
+            _ if self.to_styled().is_some() => visitor.visit_styled(self),
             _ if self.is_h() => visitor.visit_h(self),
             _ if self.is_linebreak() => visitor.visit_linebreak(self),
             _ if self.is_align_point() => visitor.visit_align_point(self),
@@ -312,10 +319,9 @@ impl ContentExt for Content {
             _ if self.is_scripts() => visitor.visit_scripts(self),
             _ if self.is_primes() => visitor.visit_primes(self),
             _ if self.is_accent() => visitor.visit_accent(self),
-            // This ignores the elements and gracefully fails;
-            // Must add results and errors eventually.
-            _ => Node::Array(vec![]),
-            // _ => panic!("Content element `{:#?}` not implemented yet.", self),
-        }
+            _ => Err(ConvertError::UnsupportedElement(self.func().name().to_string())),
+        };
+        visitor.leave(self);
+        Ok(visitor.locate(self, node?))
     }
 }
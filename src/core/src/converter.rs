@@ -2,38 +2,534 @@ use log::warn;
 
 use typst;
 use typst::foundations::Content;
+use typst::syntax::Source;
+use unicode_math_class::MathClass;
 
 use crate::katex;
 use crate::node::*;
+use crate::error::*;
 use crate::content::*;
 use crate::ext::*;
-use crate::utils::insert_separator;
+use crate::utils::{self, insert_separator, expression_range};
 use crate::symbol;
+use crate::settings::*;
+use crate::reference::EquationLabel;
+
+/// A converted tree, along with what was gathered while converting it.
+pub struct Conversion {
+    pub tree: Node,
+    pub warnings: Vec<ConvertWarning>,
+    /// The value of the equation counter after the expression, to be passed
+    /// on when converting the next one.
+    pub equation_counter: Vec<usize>,
+    pub labels: Vec<EquationLabel>,
+}
+
+pub fn convert(root: &Content, source: Option<&Source>, offset: usize, settings: &Settings) -> ConvertResult<Node> {
+    convert_with_diagnostics(root, source, offset, settings).map(|conversion| conversion.tree)
+}
 
-pub fn convert(root: &Content) -> Node {
+/// Converts a content tree. The `offset` is the length of the whitespace
+/// trimmed off the start of the expression the source was made from, so that
+/// source locations point into the expression as it was given.
+pub fn convert_with_diagnostics(
+    root: &Content,
+    source: Option<&Source>,
+    offset: usize,
+    settings: &Settings,
+) -> ConvertResult<Conversion> {
     let styles = typst::foundations::StyleChain::default();
     let mut converter = ContentConverter {
         styles: styles,
+        source: source,
+        offset: offset,
+        settings: settings,
+        display: settings.display_mode,
+        font_style: FontStyle::default(),
+        equation_counter: settings.equation_counter.clone(),
+        labels: Vec::new(),
+        depth: 0,
         parent: None,
-        position: None
+        position: None,
+        warnings: Vec::new(),
     };
-    root.accept(&mut converter)
+    let node = root.accept(&mut converter)?;
+    if settings.strict == Strict::Error {
+        if let Some(warning) = converter.warnings.first() {
+            return Err(ConvertError::Strict(warning.message.clone()));
+        }
+    }
+    Ok(Conversion {
+        tree: node,
+        warnings: converter.warnings,
+        equation_counter: converter.equation_counter,
+        labels: converter.labels,
+    })
+}
+
+/// The tree shown in place of an expression that failed to convert, when the
+/// settings ask not to throw errors.
+pub fn convert_error(expression: &str, settings: &Settings) -> Node {
+    let text = TextConverter::convert_text(expression);
+    let node = katex::ColorBuilder::default()
+        .color(settings.error_color.clone())
+        .body(text.into_array())
+        .build().unwrap().into_node();
+    // KaTeX builds the tree from an array of nodes.
+    Node::Array(vec![node])
+}
+
+/// Encloses an array in stretched delimiters, or leaves it bare when it has none.
+fn delimited(array: katex::Node, left: Option<char>, right: Option<char>) -> Node {
+    if left.is_none() && right.is_none() {
+        return Node::Node(array);
+    }
+    let leftright = katex::LeftRightBuilder::default()
+        .body([array].to_vec())
+        .left(left.map_or(".".to_string(), |c| symbol::fence(c, true)))
+        .right(right.map_or(".".to_string(), |c| symbol::fence(c, false)))
+        .build().unwrap().into_node();
+    Node::Node(leftright)
+}
+
+/// Gives nodes the label as their id, so that references can link to them.
+fn anchor(label: &str, body: katex::NodeArray) -> katex::Node {
+    katex::HtmlBuilder::default()
+        .attributes([("id".to_string(), label.to_string())].into_iter().collect())
+        .body(body)
+        .build().unwrap().into_node()
+}
+
+/// The width of `h(1fr)`, in em. It matches `\\qquad`.
+const FR_WIDTH: f32 = 2.0;
+
+/// The default gap between the rows and columns of matrices, vectors and
+/// cases in Typst, in em.
+const DEFAULT_GAP: f32 = 0.5;
+
+/// KaTeX's `\arraycolsep`, the space on either side of the boundaries between
+/// array columns, in em.
+const ARRAY_COL_SEP: f32 = 0.5;
+
+/// The font arguments of the math styles enclosing the converted content.
+#[derive(Clone, Copy, Default)]
+pub struct FontStyle {
+    pub variant: Option<typst::math::MathVariant>,
+    pub bold: Option<bool>,
+    pub italic: Option<bool>,
 }
 
 #[derive(Clone)]
 pub struct ContentConverter<'a> {
     pub styles: typst::foundations::StyleChain<'a>,
+    pub source: Option<&'a Source>,
+    pub offset: usize,
+    pub settings: &'a Settings,
+    pub display: bool,
+    pub font_style: FontStyle,
+    pub equation_counter: Vec<usize>,
+    pub labels: Vec<EquationLabel>,
+    pub depth: usize,
     pub parent: Option<Content>,
     pub position: Option<usize>,
+    pub warnings: Vec<ConvertWarning>,
+}
+
+impl ContentConverter<'_> {
+    /// Records a warning. Warnings are always returned with the tree, and the
+    /// strict setting only decides whether they are logged or fail the conversion.
+    pub fn warn(&mut self, content: &Content, mut warning: ConvertWarning) {
+        if self.settings.strict != Strict::Ignore {
+            warn!("{}", warning.message);
+        }
+        warning.loc = self.source_location(content);
+        self.warnings.push(warning);
+    }
+
+    /// Runs `f` with a converter that applies the styles on top of the current
+    /// ones, so that the getters of the elements it converts see them.
+    pub fn with_styles<T>(
+        &mut self,
+        styles: &typst::foundations::Styles,
+        f: impl FnOnce(&mut ContentConverter) -> T,
+    ) -> T {
+        // The chain borrows the outer one, which must not borrow `self`.
+        let outer = self.styles;
+        let mut converter = ContentConverter {
+            styles: outer.chain(styles),
+            source: self.source,
+            offset: self.offset,
+            settings: self.settings,
+            display: self.display,
+            font_style: self.font_style,
+            equation_counter: std::mem::take(&mut self.equation_counter),
+            labels: std::mem::take(&mut self.labels),
+            depth: self.depth,
+            parent: self.parent.clone(),
+            position: self.position,
+            warnings: std::mem::take(&mut self.warnings),
+        };
+        let result = f(&mut converter);
+        self.equation_counter = converter.equation_counter;
+        self.labels = converter.labels;
+        self.warnings = converter.warnings;
+        result
+    }
+
+    /// The style of the equation being converted, in display or inline mode.
+    pub fn style(&self) -> katex::StyleStr {
+        if self.display {
+            katex::StyleStr::Display
+        } else {
+            katex::StyleStr::Text
+        }
+    }
+
+    /// The KaTeX font closest to the font style, or `None` for the default
+    /// math font. KaTeX fonts do not combine, so a nested font must account
+    /// for the enclosing ones.
+    pub fn font(&mut self, content: &Content, style: FontStyle) -> Option<&'static str> {
+        use typst::math::MathVariant;
+
+        let variant = style.variant.unwrap_or(MathVariant::Serif);
+        let bold = style.bold.unwrap_or(false);
+        let italic = style.italic;
+
+        let (font, is_bold, is_italic) = match (variant, bold, italic) {
+            (MathVariant::Serif, false, None) => return None,
+            (MathVariant::Serif, false, Some(false)) => ("mathrm", false, Some(false)),
+            (MathVariant::Serif, false, Some(true)) => ("mathit", false, Some(true)),
+            (MathVariant::Serif, true, Some(false)) => ("mathbf", true, Some(false)),
+            (MathVariant::Serif, true, _) => ("boldsymbol", true, italic),
+            (MathVariant::Sans, _, _) => ("mathsf", false, Some(false)),
+            (MathVariant::Cal, _, _) => ("mathcal", false, italic),
+            (MathVariant::Frak, _, _) => ("mathfrak", false, italic),
+            (MathVariant::Mono, _, _) => ("mathtt", false, Some(false)),
+            (MathVariant::Bb, _, _) => ("mathbb", false, italic),
+        };
+
+        if bold != is_bold {
+            self.warn(content, ConvertWarning::unsupported_argument("math-style", "bold"));
+        }
+        if italic == Some(true) && is_italic != Some(true) {
+            self.warn(content, ConvertWarning::unsupported_argument("math-style", "italic"));
+        }
+        Some(font)
+    }
+
+    /// The length in em, with its absolute part relative to the current font
+    /// size, the same way Typst resolves em lengths.
+    pub fn em(&self, length: typst::layout::Length) -> f64 {
+        let font_size = typst::text::TextElem::size_in(self.styles);
+        length.em.get() + length.abs / font_size
+    }
+
+    /// The absolute part of a length. KaTeX has no lengths relative to the
+    /// enclosing region, so the relative part is dropped.
+    pub fn absolute(
+        &mut self,
+        content: &Content,
+        argument: &str,
+        length: typst::layout::Rel<typst::layout::Length>,
+    ) -> typst::layout::Length {
+        if !length.rel.is_zero() {
+            let element = content.func().name();
+            let message = format!("Relative lengths are unsupported for argument `{}` of element `{}`.", argument, element);
+            self.warn(content, ConvertWarning::new(element, message));
+        }
+        length.abs
+    }
+
+    /// Converts a length into a KaTeX measurement.
+    pub fn measurement(
+        &mut self,
+        content: &Content,
+        argument: &str,
+        length: typst::layout::Rel<typst::layout::Length>,
+    ) -> katex::Measurement {
+        let length = self.absolute(content, argument, length);
+        utils::measurement(length, typst::text::TextElem::size_in(self.styles))
+    }
+
+    /// The row gaps of an array with the given gap between its rows, or `None`
+    /// for Typst's default gap, which KaTeX's row spacing already matches.
+    pub fn row_gaps(
+        &mut self,
+        content: &Content,
+        argument: &str,
+        gap: typst::layout::Rel<typst::layout::Abs>,
+        rows: usize,
+    ) -> Option<Vec<Option<katex::Measurement>>> {
+        let gap = typst::layout::Rel::new(gap.rel, gap.abs.into());
+        let gap = self.absolute(content, argument, gap);
+        let number = self.em(gap) as f32 - DEFAULT_GAP;
+        if number == 0.0 {
+            return None;
+        }
+        let measurement = katex::Measurement {
+            number,
+            unit: "em".to_string(),
+        };
+        Some(vec![Some(measurement); rows.saturating_sub(1)])
+    }
+
+    /// The space on either side of the boundaries between the columns of an
+    /// array in em, or `None` for Typst's default gap, which keeps KaTeX's
+    /// column spacing. Other gaps scale KaTeX's spacing in proportion.
+    pub fn column_gap(
+        &mut self,
+        content: &Content,
+        argument: &str,
+        gap: typst::layout::Rel<typst::layout::Abs>,
+    ) -> Option<f32> {
+        let gap = typst::layout::Rel::new(gap.rel, gap.abs.into());
+        let gap = self.absolute(content, argument, gap);
+        let number = self.em(gap) as f32;
+        (number != DEFAULT_GAP).then_some(ARRAY_COL_SEP * number / DEFAULT_GAP)
+    }
+
+    /// Whether the content is weak spacing.
+    pub fn is_weak_spacing(&self, content: &Content) -> bool {
+        content.is_h() && content.to_h().weak(self.styles)
+    }
+
+    /// The KaTeX delimiter size closest to an `lr` size, or `None` for the
+    /// natural size. KaTeX sizes do not depend on the wrapped content, so this
+    /// only holds for content of a single line of text.
+    pub fn delimiter_size(&self, size: typst::layout::Rel<typst::layout::Length>) -> Option<katex::SizeType> {
+        let height = size.rel.get() + self.em(size.abs);
+
+        // Reference: delimiter.js, sizeToMaxHeight
+        if height <= 1.0 {
+            None
+        } else if height <= 1.2 {
+            Some(katex::SizeType::One)
+        } else if height <= 1.8 {
+            Some(katex::SizeType::Two)
+        } else if height <= 2.4 {
+            Some(katex::SizeType::Three)
+        } else {
+            Some(katex::SizeType::Four)
+        }
+    }
+
+    /// Adds the lines of a matrix augmentation to the array, with the offsets
+    /// checked the same way Typst does.
+    pub fn augment(
+        &mut self,
+        content: &Content,
+        constructor: &mut katex::ArrayConstructor,
+        augment: typst::math::Augment<typst::layout::Abs>,
+    ) -> ConvertResult<()> {
+        let nrows = constructor.count_rows();
+        let ncols = constructor.body.first().map_or(0, |row| row.len());
+
+        for offset in augment.hline.lines()? {
+            if offset == 0 || offset.unsigned_abs() >= nrows {
+                return Err(ConvertError::MalformedContent(format!(
+                    "Cannot draw a horizontal line after row {} of a matrix with {} rows.",
+                    if offset < 0 { nrows as isize + offset } else { offset },
+                    nrows,
+                )));
+            }
+            let row = if offset < 0 { nrows - offset.unsigned_abs() } else { offset as usize };
+            constructor.hline_before_row(row);
+        }
+
+        for offset in augment.vline.lines()? {
+            if offset == 0 || offset.unsigned_abs() >= ncols {
+                return Err(ConvertError::MalformedContent(format!(
+                    "Cannot draw a vertical line after column {} of a matrix with {} columns.",
+                    if offset < 0 { ncols as isize + offset } else { offset },
+                    ncols,
+                )));
+            }
+            let column = if offset < 0 { ncols - offset.unsigned_abs() } else { offset as usize };
+            constructor.vline_after_column(column - 1);
+        }
+
+        // KaTeX draws array lines with the rule thickness of the font.
+        if augment.stroke.is_custom() {
+            self.warn(content, ConvertWarning::unsupported_argument("mat", "augment stroke"));
+        }
+        Ok(())
+    }
+
+    /// The numbering pattern of an equation, if it is numbered. Like in Typst,
+    /// only display equations are numbered.
+    pub fn numbering(&mut self, content: &Content) -> Option<typst::model::NumberingPattern> {
+        if !self.display {
+            return None;
+        }
+        match content.to_equation().numbering(self.styles) {
+            Some(typst::model::Numbering::Pattern(pattern)) => return Some(pattern),
+            Some(typst::model::Numbering::Func(_)) => {
+                self.warn(content, ConvertWarning::unsupported_argument("equation", "numbering"));
+                return None;
+            },
+            None => (),
+        }
+        let numbering = self.settings.numbering.as_ref()?;
+        match numbering.parse() {
+            Ok(pattern) => Some(pattern),
+            Err(_) => {
+                let message = format!("Invalid numbering pattern `{}`.", numbering);
+                self.warn(content, ConvertWarning::new("equation", message));
+                None
+            },
+        }
+    }
+
+    /// Steps the equation counter, and formats its new value with the pattern.
+    pub fn step_equation_counter(&mut self, pattern: &typst::model::NumberingPattern) -> String {
+        if self.equation_counter.is_empty() {
+            self.equation_counter.push(0);
+        }
+        *self.equation_counter.last_mut().unwrap() += 1;
+        pattern.apply(&self.equation_counter).to_string()
+    }
+
+    /// The text put before the number of an equation in references.
+    pub fn supplement(&mut self, content: &Content) -> Option<String> {
+        match content.to_equation().supplement(self.styles) {
+            typst::foundations::Smart::Auto => Some("Equation".to_string()),
+            typst::foundations::Smart::Custom(None) => None,
+            typst::foundations::Smart::Custom(Some(typst::model::Supplement::Content(supplement))) => {
+                Some(supplement.plain_text().to_string())
+            },
+            typst::foundations::Smart::Custom(Some(typst::model::Supplement::Func(_))) => {
+                self.warn(content, ConvertWarning::unsupported_argument("equation", "supplement"));
+                None
+            },
+        }
+    }
+
+    /// The location of the content within the converted expression.
+    pub fn source_location(&self, content: &Content) -> Option<katex::SourceLocation> {
+        let range = expression_range(self.source?, content.span(), self.offset)?;
+        Some(katex::SourceLocation {
+            start: range.start,
+            end: range.end,
+        })
+    }
+
+    /// Whether the text was written as a string, like `"if"`, rather than
+    /// produced by an identifier.
+    pub fn is_quoted(&self, content: &Content) -> bool {
+        let range = self.source.and_then(|source| source.range(content.span()));
+        match (self.source, range) {
+            (Some(source), Some(range)) => source.text()[range].trim_start_matches('#').starts_with('"'),
+            // Without the source, only text that cannot be an identifier is a string.
+            _ => !content.to_text().text().chars().all(char::is_alphanumeric),
+        }
+    }
 }
 
 impl ContentVisitor for ContentConverter<'_> {
-    fn visit_equation(&mut self, content: &Content) -> Node {
+    fn enter(&mut self, content: &Content) -> ConvertResult<()> {
+        if self.depth >= self.settings.max_expand {
+            return Err(ConvertError::TooDeeplyNested(self.settings.max_expand));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self, content: &Content) {
+        self.depth -= 1;
+    }
+
+    fn locate(&self, content: &Content, node: Node) -> Node {
+        let Some(loc) = self.source_location(content) else {
+            return node;
+        };
+        // Nodes located by the content they were converted from keep their location.
+        match node {
+            Node::Node(mut node) => {
+                node.loc_mut().get_or_insert(loc);
+                Node::Node(node)
+            },
+            Node::Array(mut array) => {
+                for node in array.iter_mut() {
+                    node.loc_mut().get_or_insert(loc.clone());
+                }
+                Node::Array(array)
+            },
+        }
+    }
+
+    fn visit_equation(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_equation();
-        Node::Array(elem.body().accept(self).into_array())
+
+        let _body = elem.body();
+        let _block = elem.block(self.styles); // always false, see `utils::eval`
+
+        self.display = self.settings.display_mode;
+        let mut body = _body.accept(self)?;
+
+        // Aligned equations are numbered line by line, the others as a whole.
+        let label = self.settings.label.clone();
+        let mut numbers = Vec::new();
+        let mut tag = None;
+        let mut is_row_tagged = false;
+        if let Some(pattern) = self.numbering(content) {
+            match body {
+                Node::Node(katex::Node::Array(ref mut array))
+                    if matches!(array.col_separation_type, Some(katex::ColSeparationType::Align)) =>
+                {
+                    numbers = array.body.iter().map(|_| self.step_equation_counter(&pattern)).collect();
+                    let mut tags: Vec<_> = numbers.iter()
+                        .map(|number| TextConverter::convert_text(number).into_array())
+                        .collect();
+                    // KaTeX lays the tags out beside the array only when nothing
+                    // wraps it, so the label goes on the first tag instead.
+                    if let (Some(label), Some(first)) = (&label, tags.first_mut()) {
+                        *first = vec![anchor(label, std::mem::take(first))];
+                    }
+                    array.tags = Some(tags.into_iter().map(katex::TagType::NodeArray).collect());
+                    is_row_tagged = true;
+                },
+                _ => {
+                    let number = self.step_equation_counter(&pattern);
+                    tag = Some(TextConverter::convert_text(&number).into_array());
+                    numbers.push(number);
+                },
+            }
+        }
+
+        // The style is set explicitly, so that the tree lays out the same way
+        // regardless of the display mode KaTeX renders it with.
+        let mut node = katex::StylingBuilder::default()
+            .style(self.style())
+            .body(body.into_array())
+            .build().unwrap().into_node();
+
+        // A labelled equation is given the label as its id, so that references
+        // can link to it. The tag stays outermost, where KaTeX looks for it.
+        if let Some(label) = label {
+            let supplement = self.supplement(content);
+            if !is_row_tagged {
+                node = anchor(&label, [node].to_vec());
+            }
+            self.labels.push(EquationLabel {
+                label,
+                number: numbers.first().cloned(),
+                supplement,
+            });
+        }
+
+        // KaTeX builds the tree from an array of nodes, and only finds the tag
+        // when it is the single node of that array.
+        let Some(tag) = tag else {
+            return Ok(Node::Array(vec![node]));
+        };
+        let node = katex::TagBuilder::default()
+            .body([node].to_vec())
+            .tag(tag)
+            .build().unwrap().into_node();
+        Ok(Node::Array(vec![node]))
     }
 
-    fn visit_op(&mut self, content: &Content) -> Node {
+    fn visit_op(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_op();
 
         let _text = elem.text();
@@ -47,17 +543,21 @@ impl ContentVisitor for ContentConverter<'_> {
             .symbol(false)
             .name(Some(name))
             .build().unwrap().into_node();
-        Node::Node(node)
+        Ok(Node::Node(node))
     }
 
-    fn visit_mat(&mut self, content: &Content) -> Node {
+    fn visit_mat(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_mat();
         let mut constructor = katex::ArrayConstructor::default();
 
+        let _augment = elem.augment(self.styles);
+        let _row_gap = elem.row_gap(self.styles);
+        let _column_gap = elem.column_gap(self.styles);
+
         for row in elem.rows() {
             constructor.next_row();
             for content in row {
-                let node = content.accept(self);
+                let node = content.accept(self)?;
                 let ordgroup = katex::OrdGroupBuilder::default()
                     .body(node.into_array())
                     .build().unwrap().into_node();
@@ -68,33 +568,38 @@ impl ContentVisitor for ContentConverter<'_> {
                 constructor.push_node(styling);
             }
         }
-        let array = constructor.builder().build().unwrap().into_node();
-        let delim = elem.delim(self.styles).unwrap();
-        let leftright = katex::LeftRightBuilder::default()
-            .body([array].to_vec())
-            .left(delim.open().to_string())
-            .right(delim.close().to_string())
-            .build().unwrap().into_node();
-        Node::Node(leftright)
+        if let Some(augment) = _augment {
+            self.augment(content, &mut constructor, augment)?;
+        }
+        if let Some(sep) = self.column_gap(content, "column-gap", _column_gap) {
+            constructor.cols_sep(sep);
+        }
+        let mut builder = constructor.builder();
+        if let Some(row_gaps) = self.row_gaps(content, "row-gap", _row_gap, constructor.count_rows()) {
+            builder.row_gaps(row_gaps);
+        }
+        let array = builder.build().unwrap().into_node();
+        let delim = elem.delim(self.styles);
+        Ok(delimited(array, delim.map(|d| d.open()), delim.map(|d| d.close())))
     }
 
-    fn visit_vec(&mut self, content: &Content) -> Node {
-        let mut converter = VecConverter::new(content.to_vec());
+    fn visit_vec(&mut self, content: &Content) -> ConvertResult<Node> {
+        let mut converter = VecConverter::new(content);
         converter.convert(self)
     }
 
-    fn visit_frac(&mut self, content: &Content) -> Node {
+    fn visit_frac(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_frac();
 
         let _num = elem.num();
         let _denom = elem.denom();
 
         let numer = katex::OrdGroupBuilder::default()
-            .body(_num.accept(self).into_array())
+            .body(_num.accept(self)?.into_array())
             .build().unwrap().into_node();
 
         let denom = katex::OrdGroupBuilder::default()
-            .body(_denom.accept(self).into_array())
+            .body(_denom.accept(self)?.into_array())
             .build().unwrap().into_node();
 
         let node = katex::GenFracBuilder::default()
@@ -105,30 +610,41 @@ impl ContentVisitor for ContentConverter<'_> {
             .size(katex::GenFracSizeType::Auto)
             .continued(false)
             .build().unwrap().into_node();
-        Node::Node(node)
+        Ok(Node::Node(node))
     }
 
-    fn visit_align_point(&mut self, content: &Content) -> Node {
+    fn visit_align_point(&mut self, content: &Content) -> ConvertResult<Node> {
         let node = katex::OrdGroupBuilder::default().build().unwrap().into_node();
-        Node::Node(node)
+        Ok(Node::Node(node))
     }
 
-    fn visit_linebreak(&mut self, content: &Content) -> Node {
+    fn visit_linebreak(&mut self, content: &Content) -> ConvertResult<Node> {
         let node = katex::CrBuilder::default()
             .new_line(true)
             .build().unwrap().into_node();
-        Node::Array(vec![node])
+        Ok(Node::Array(vec![node]))
     }
 
-    fn visit_sequence(&mut self, content: &Content) -> Node {
+    fn visit_sequence(&mut self, content: &Content) -> ConvertResult<Node> {
         let mut converter = SequenceConverter::new(content);
         converter.convert(self)
     }
 
-    fn visit_space(&mut self, content: &Content) -> Node {
-        let sequence: Vec<_> = self.parent.as_ref().unwrap().to_sequence().unwrap().collect();
-        let left = sequence.get(self.position.unwrap() - 1);
-        let right = sequence.get(self.position.unwrap() + 1);
+    fn visit_space(&mut self, content: &Content) -> ConvertResult<Node> {
+        let (Some(parent), Some(position)) = (self.parent.as_ref(), self.position) else {
+            return Ok(Node::Array(Vec::new()));
+        };
+        let sequence: Vec<_> = match parent.to_sequence() {
+            Some(sequence) => sequence.collect(),
+            None => return Ok(Node::Array(Vec::new())),
+        };
+        let left = position.checked_sub(1).and_then(|i| sequence.get(i));
+        let right = sequence.get(position + 1);
+        // Like in Typst, spaces only separate things, so those at the ends of
+        // a sequence are dropped. This includes the newline ending the source.
+        if left.is_none() || right.is_none() {
+            return Ok(Node::Array(Vec::new()));
+        }
 
         fn induced_space(elem: &Content) -> Option<Node> {
             if elem.is_text() {
@@ -147,7 +663,8 @@ impl ContentVisitor for ContentConverter<'_> {
                         _ => return None
                     }
                 }
-                if text.chars().count() > 1 {
+                // Numbers are not spaced, unlike words.
+                if text.chars().count() > 1 && !TextConverter::is_number(text) {
                     let node = katex::SpacingBuilder::default()
                         .mode(katex::Mode::Math)
                         .text("\\ ".to_string())
@@ -160,32 +677,51 @@ impl ContentVisitor for ContentConverter<'_> {
 
         if let Some(elem) = right {
             if elem.is_linebreak() || elem.is_align_point() {
-                return Node::Array(Vec::new());
+                return Ok(Node::Array(Vec::new()));
             }
             if let Some(space) = induced_space(elem) {
-                return space;
+                return Ok(space);
             }
         }
         if let Some(elem) = left {
             if let Some(space) = induced_space(elem) {
-                return space;
+                return Ok(space);
             }
         }
-        return Node::Array(Vec::new());
+        return Ok(Node::Array(Vec::new()));
     }
 
-    fn visit_text(&mut self, content: &Content) -> Node {
-        let mut text_converter = TextConverter::new(content.to_text());
-        text_converter.convert()
+    fn visit_text(&mut self, content: &Content) -> ConvertResult<Node> {
+        let mut text_converter = TextConverter::new(content);
+        Ok(text_converter.convert(self))
     }
 
-    fn visit_lr(&mut self, content: &Content) -> Node {
+    fn visit_lr(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_lr();
 
         let _body = elem.body();
-        let _size = elem.size(self.styles); // unsupported
+        let _size = elem.size(self.styles);
 
-        let mut body = _body.accept(self).into_array();
+        // Like Typst, the delimiters of a nested `lr` are the ones being sized.
+        let _body = match _body.is_lr() && _body.to_lr().size(self.styles).is_auto() {
+            true => _body.to_lr().body(),
+            false => _body,
+        };
+
+        // Weak spacing right after the opening or before the closing delimiter
+        // is removed, as in Typst.
+        let _body = if _body.is_sequence() {
+            let children: Vec<_> = _body.to_sequence().unwrap().collect();
+            let len = children.len();
+            let children = children.into_iter().enumerate()
+                .filter(|&(i, child)| !((i == 1 || i + 2 == len) && self.is_weak_spacing(child)))
+                .map(|(_, child)| child.clone());
+            Content::sequence(children)
+        } else {
+            _body
+        };
+
+        let mut body = _body.accept(self)?.into_array();
 
         // TODO: Another case to treat is when there's a styling node (\textstyle) as parent,
         // and in this case maybe a context object will be needed.
@@ -200,98 +736,260 @@ impl ContentVisitor for ContentConverter<'_> {
             }
         }
 
-        if !is_lr {
-            return Node::Array(body.clone());
+        // Only the ends of the body are scaled, and only when they are symbols.
+        // A single node is scaled as an opening delimiter.
+        let left = body.first().and_then(|node| symbol::delimiter(node, true));
+        let right = match body.len() {
+            0 | 1 => None,
+            _ => body.last().and_then(|node| symbol::delimiter(node, false)),
+        };
+
+        if let typst::foundations::Smart::Custom(size) = _size {
+            // A single line is sized with KaTeX's fixed delimiter sizes.
+            if !is_lr {
+                let Some(size) = self.delimiter_size(size) else {
+                    return Ok(Node::Array(body));
+                };
+                if let Some(delim) = right {
+                    *body.last_mut().unwrap() = katex::DelimSizingBuilder::default()
+                        .size(size.clone())
+                        .mclass(katex::MClassType::MClose)
+                        .delim(delim)
+                        .build().unwrap().into_node();
+                }
+                if let Some(delim) = left {
+                    body[0] = katex::DelimSizingBuilder::default()
+                        .size(size)
+                        .mclass(katex::MClassType::MOpen)
+                        .delim(delim)
+                        .build().unwrap().into_node();
+                }
+                return Ok(Node::Array(body));
+            }
+            // Taller content is wrapped in stretched delimiters, which KaTeX
+            // sizes to the content itself.
+            if size != typst::layout::Rel::one() {
+                self.warn(content, ConvertWarning::new("lr", "The size of `lr` around tall content is approximated by the height of the content.".to_string()));
+            }
         }
 
-        let left = match body.remove(0) {
-            katex::Node::Atom(atom) => atom.text,
-            _ => panic!("Not an atom!"),
-        };
-        let right = match body.pop().unwrap() {
-            katex::Node::Atom(atom) => atom.text,
-            _ => panic!("Not an atom!"),
-        };
+        if !is_lr || (left.is_none() && right.is_none()) {
+            return Ok(Node::Array(body));
+        }
+        if left.is_some() {
+            body.remove(0);
+        }
+        if right.is_some() {
+            body.pop();
+        }
 
         let node = katex::LeftRightBuilder::default()
             .body(body)
-            .left(left)
-            .right(right)
+            .left(left.unwrap_or(".".to_string()))
+            .right(right.unwrap_or(".".to_string()))
             .build().unwrap().into_node();
-        Node::Node(node)
+        Ok(Node::Node(node))
     }
 
-    fn visit_attach(&mut self, content: &Content) -> Node {
+    fn visit_attach(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_attach();
 
         let _base = elem.base();
         let _t = elem.t(self.styles);
         let _b = elem.b(self.styles);
-        let _tl = elem.tl(self.styles); // unsupported
-        let _bl = elem.bl(self.styles); // unsupported
-        let _tr = elem.tr(self.styles); // unsupported
-        let _br = elem.br(self.styles); // unsupported
-        if _tl.is_some() { warn!("Top left element is unsupported."); }
-        if _tr.is_some() { warn!("Top right element is unsupported."); }
-        if _bl.is_some() { warn!("Bottom left element is unsupported."); }
-        if _br.is_some() { warn!("Bottom right element is unsupported."); }
-
-        let base = _base.accept(self).into_node_fallback_ordgroup(katex::Mode::Math);
-        let sup = _t.map(|c| c.accept(self)).map(|n| n.into_node_fallback_ordgroup(katex::Mode::Math));
-        let sub = _b.map(|c| c.accept(self)).map(|n| n.into_node_fallback_ordgroup(katex::Mode::Math));
+        let _tl = elem.tl(self.styles);
+        let _bl = elem.bl(self.styles);
+        let _tr = elem.tr(self.styles);
+        let _br = elem.br(self.styles);
+
+        let primed = matches!(_tr, Some(ref c) if c.is_primes());
+
+        let base = _base.accept(self)?.into_node_fallback_ordgroup(katex::Mode::Math);
+        let limits = matches!(base, katex::Node::Op(ref op) if op.limits);
+
+        let t = _t.map(|c| c.accept(self)).transpose()?;
+        let tr = _tr.map(|c| c.accept(self)).transpose()?;
+        // Primes are attached at the top right, and a superscript continues right
+        // after them, like KaTeX does for `f'^2`.
+        let (t, tr) = match (t, tr) {
+            (Some(t), Some(tr)) if primed && !limits => {
+                (None, Some(Node::Array([tr.into_array(), t.into_array()].concat())))
+            },
+            (t, tr) => (t, tr),
+        };
 
-        let node = katex::SupSubBuilder::default()
-            .base(Some(base).map(Box::new))
-            .sup(sup.map(Box::new))
-            .sub(sub.map(Box::new))
+        let t = t.map(|n| n.into_node_fallback_ordgroup(katex::Mode::Math));
+        let b = _b.map(|c| c.accept(self)).transpose()?.map(|n| n.into_node_fallback_ordgroup(katex::Mode::Math));
+        let tl = _tl.map(|c| c.accept(self)).transpose()?.map(|n| n.into_node_fallback_ordgroup(katex::Mode::Math));
+        let bl = _bl.map(|c| c.accept(self)).transpose()?.map(|n| n.into_node_fallback_ordgroup(katex::Mode::Math));
+        let tr = tr.map(|n| n.into_node_fallback_ordgroup(katex::Mode::Math));
+        let br = _br.map(|c| c.accept(self)).transpose()?.map(|n| n.into_node_fallback_ordgroup(katex::Mode::Math));
+
+        // Same positioning rules as Typst: `t` and `b` only go above and below the
+        // base when it has limits or when the corresponding right script is taken.
+        let (t, tr) = if limits || tr.is_some() { (t, tr) } else { (None, t) };
+        let (b, br) = if limits || br.is_some() { (b, br) } else { (None, b) };
+
+        let mut node = base.clone();
+        if t.is_some() || b.is_some() {
+            if limits {
+                node = katex::SupSubBuilder::default()
+                    .base(Some(Box::new(node)))
+                    .sup(t.map(Box::new))
+                    .sub(b.map(Box::new))
+                    .build().unwrap().into_node();
+            } else {
+                // Stack the scripts over the base like `\overset` does.
+                let op = katex::OpBuilder::default()
+                    .limits(true)
+                    .always_handle_sup_sub(Some(true))
+                    .suppress_base_shift(Some(true))
+                    .parent_is_sup_sub(false)
+                    .symbol(false)
+                    .body(Some([node].to_vec()))
+                    .build().unwrap().into_node();
+                let supsub = katex::SupSubBuilder::default()
+                    .base(Some(Box::new(op)))
+                    .sup(t.map(Box::new))
+                    .sub(b.map(Box::new))
+                    .build().unwrap().into_node();
+                node = katex::MClassBuilder::default()
+                    .mclass(katex::MClassType::MOrd)
+                    .body([supsub].to_vec())
+                    .is_character_box(false)
+                    .build().unwrap().into_node();
+            }
+        }
+        if tr.is_some() || br.is_some() {
+            node = katex::SupSubBuilder::default()
+                .base(Some(Box::new(node)))
+                .sup(tr.map(Box::new))
+                .sub(br.map(Box::new))
+                .build().unwrap().into_node();
+        }
+        if tl.is_none() && bl.is_none() {
+            return Ok(Node::Node(node));
+        }
+
+        // Left scripts are attached to a phantom copy of the base, so that they
+        // are positioned according to its height and depth.
+        let phantom = katex::VPhantomBuilder::default()
+            .body(Box::new(base))
             .build().unwrap().into_node();
-        Node::Node(node)
+        let prescripts = katex::SupSubBuilder::default()
+            .base(Some(Box::new(Node::Node(phantom).into_ordgroup(katex::Mode::Math).into_node())))
+            .sup(tl.map(Box::new))
+            .sub(bl.map(Box::new))
+            .build().unwrap().into_node();
+        Ok(Node::Array([prescripts, node].to_vec()))
     }
 
-    fn visit_math_style(&mut self, content: &Content) -> Node {
+    fn visit_math_style(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_math_style();
 
         let _body = elem.body();
         let _variant = elem.variant(self.styles);
-        let _bold = elem.bold(self.styles); // unsupported
-        let _italic = elem.italic(self.styles); // unsupported
-        let _size = elem.size(self.styles); // unsupported
-        let _cramped = elem.cramped(self.styles); // unsupported
-        if _bold.is_some() { warn!("Bold options are unsupported."); }
-        if _italic.is_some() { warn!("Italic options are unsupported."); }
-        if _size.is_some() { warn!("Size options are unsupported."); }
-        if _cramped.is_some() { warn!("Cramped options are unsupported."); }
-
-        let body = _body.accept(self).into_node().unwrap();
-        let font = match _variant {
-            Some(typst::math::MathVariant::Bb) => "mathbb",
-            Some(typst::math::MathVariant::Cal) => "mathcal",
-            Some(typst::math::MathVariant::Serif) => "mathrm",
-            Some(typst::math::MathVariant::Sans) => "mathsf",
-            Some(typst::math::MathVariant::Frak) => "mathfrak",
-            Some(typst::math::MathVariant::Mono) => "mathtt",
-            None => "mathnormal"
-        }.to_string();
+        let _bold = elem.bold(self.styles);
+        let _italic = elem.italic(self.styles);
+        let _size = elem.size(self.styles);
+        let _cramped = elem.cramped(self.styles);
+        // KaTeX has no cramped styles in its parse tree. Scripts are cramped by
+        // default in Typst, and only differ slightly from KaTeX's script styles.
+        let is_script = matches!(_size, Some(typst::math::MathSize::Script | typst::math::MathSize::ScriptScript));
+        if _cramped == Some(true) && !is_script {
+            self.warn(content, ConvertWarning::unsupported_argument("math-style", "cramped"));
+        }
 
-        let node = katex::FontBuilder::default()
-            .body(Box::new(body))
-            .font(font)
+        let outer = self.font_style;
+        let style = FontStyle {
+            variant: _variant.or(outer.variant),
+            bold: _bold.or(outer.bold),
+            italic: _italic.or(outer.italic),
+        };
+
+        self.font_style = style;
+        let body = _body.accept(self);
+        self.font_style = outer;
+        let body = body?;
+
+        // Only restate the font when this element changes it, so that a size
+        // style does not reset the font to the default one.
+        let is_font_style = _variant.is_some() || _bold.is_some() || _italic.is_some();
+        let is_nested = outer.variant.is_some() || outer.bold.is_some() || outer.italic.is_some();
+        let font = match is_font_style {
+            true => self.font(content, style).or(is_nested.then_some("mathnormal")),
+            false => None,
+        };
+        let node = match font {
+            Some(font) => {
+                let node = katex::FontBuilder::default()
+                    .body(Box::new(body.into_node_fallback_ordgroup(katex::Mode::Math)))
+                    .font(font.to_string())
+                    .build().unwrap().into_node();
+                Node::Node(node)
+            },
+            None => body,
+        };
+
+        let Some(size) = _size else {
+            return Ok(node);
+        };
+        let style = match size {
+            typst::math::MathSize::Display => katex::StyleStr::Display,
+            typst::math::MathSize::Text => katex::StyleStr::Text,
+            typst::math::MathSize::Script => katex::StyleStr::Script,
+            typst::math::MathSize::ScriptScript => katex::StyleStr::ScriptScript,
+        };
+        let node = katex::StylingBuilder::default()
+            .style(style)
+            .body(node.into_array())
             .build().unwrap().into_node();
-        Node::Node(node)
+        Ok(Node::Node(node))
+    }
+
+    fn visit_styled(&mut self, content: &Content) -> ConvertResult<Node> {
+        let (_child, _styles) = content.to_styled().unwrap();
+
+        // Show rules only take effect when the document is laid out.
+        if _styles.iter().any(|style| style.recipe().is_some()) {
+            self.warn(content, ConvertWarning::new("styled", "Show rules are not applied.".to_string()));
+        }
+
+        let outer = typst::text::TextElem::fill_in(self.styles);
+        let _fill = typst::text::TextElem::fill_in(self.styles.chain(_styles));
+
+        let body = self.with_styles(_styles, |converter| _child.accept(converter))?;
+
+        // Only restate the color when these styles change it.
+        if _fill == outer {
+            return Ok(body);
+        }
+        let color = match _fill {
+            typst::visualize::Paint::Solid(color) => color.css(),
+            _ => {
+                self.warn(content, ConvertWarning::new("text", "Only solid colors are supported for the fill of text.".to_string()));
+                return Ok(body);
+            },
+        };
+        let node = katex::ColorBuilder::default()
+            .color(color)
+            .body(body.into_array())
+            .build().unwrap().into_node();
+        Ok(Node::Node(node))
     }
 
-    fn visit_binom(&mut self, content: &Content) -> Node {
+    fn visit_binom(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_binom();
 
         let _upper = elem.upper();
         let _lower = elem.lower();
 
         let numer = katex::OrdGroupBuilder::default()
-            .body(_upper.accept(self).into_array())
+            .body(_upper.accept(self)?.into_array())
             .build().unwrap().into_node();
 
         let separator = katex::Symbol::get(katex::Mode::Math, ',').create_node();
-        let denom_body_parts: Vec<katex::NodeArray> = elem.lower().iter().map(|c| c.accept(self).into_array()).collect();
+        let denom_body_parts: Vec<katex::NodeArray> = elem.lower().iter().map(|c| c.accept(self).map(|n| n.into_array())).collect::<ConvertResult<_>>()?;
         let denom_body = insert_separator(&denom_body_parts, [separator].to_vec()).iter().flatten().cloned().collect();
         let denom = katex::OrdGroupBuilder::default()
             .body(denom_body)
@@ -306,60 +1004,85 @@ impl ContentVisitor for ContentConverter<'_> {
             .right_delim(")".to_string())
             .size(katex::GenFracSizeType::Auto)
             .build().unwrap().into_node();
-        Node::Node(node)
+        Ok(Node::Node(node))
     }
 
-    fn visit_cancel(&mut self, content: &Content) -> Node {
+    fn visit_cancel(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_cancel();
 
         let _body = elem.body();
         let _length = elem.length(self.styles); // unsupported
-        let _inverted = elem.inverted(self.styles); // unsupported
-        let _cross = elem.cross(self.styles); // unsupported
+        let _inverted = elem.inverted(self.styles);
+        let _cross = elem.cross(self.styles);
         let _angle = elem.angle(self.styles); // unsupported
-        let _stroke = elem.stroke(self.styles); // unsupported
+        let _stroke = elem.stroke(self.styles);
+        if content.field("length").is_some() { self.warn(content, ConvertWarning::unsupported_argument("cancel", "length")); }
+        if !_angle.is_auto() { self.warn(content, ConvertWarning::unsupported_argument("cancel", "angle")); }
+
+        // KaTeX draws the line with a fixed thickness, so only its paint is kept.
+        let color = match _stroke.paint {
+            typst::foundations::Smart::Custom(typst::visualize::Paint::Solid(color)) => Some(color.css()),
+            typst::foundations::Smart::Custom(_) => {
+                self.warn(content, ConvertWarning::new("cancel", "Only solid colors are supported for the stroke of `cancel`.".to_string()));
+                None
+            },
+            typst::foundations::Smart::Auto => None,
+        };
+        if _stroke.thickness != typst::foundations::Smart::Custom(typst::layout::Abs::pt(0.5)) {
+            self.warn(content, ConvertWarning::new("cancel", "The stroke thickness of `cancel` is unsupported.".to_string()));
+        }
+
+        let label = if _cross {
+            "\\xcancel"
+        } else if _inverted {
+            "\\bcancel"
+        } else {
+            "\\cancel"
+        };
 
         let body = katex::OrdGroupBuilder::default()
-            .body(_body.accept(self).into_array())
+            .body(_body.accept(self)?.into_array())
             .build().unwrap().into_node();
 
         let node = katex::EncloseBuilder::default()
-            .label("\\cancel".to_string())
+            .label(label.to_string())
+            .border_color(color)
             .body(Box::new(body))
             .build().unwrap().into_node();
-        Node::Node(node)
+        Ok(Node::Node(node))
     }
 
-    fn visit_cases(&mut self, content: &Content) -> Node {
-        let mut converter = CasesConverter::new(content.to_cases());
+    fn visit_cases(&mut self, content: &Content) -> ConvertResult<Node> {
+        let mut converter = CasesConverter::new(content);
         converter.convert(self)
     }
 
-    fn visit_limits(&mut self, content: &Content) -> Node {
+    fn visit_limits(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_limits();
 
         let _body = elem.body();
-        let _inline = elem.inline(self.styles); // unsupported
+        let _inline = elem.inline(self.styles);
 
-        let body = _body.accept(self).into_array();
+        let body = _body.accept(self)?.into_array();
 
-        // This comes inside an AttachElem, so we have to transform this into an operator
+        // This comes inside an AttachElem, so we have to transform this into an operator.
+        // Without `inline`, inline equations attach the scripts to the side.
         let node = katex::OpBuilder::default()
             .mode(katex::Mode::Math)
-            .limits(true)
+            .limits(_inline || self.display)
             .parent_is_sup_sub(false)
             .symbol(false)
             .body(Some(body))
             .build().unwrap().into_node();
-        Node::Node(node)
+        Ok(Node::Node(node))
     }
 
-    fn visit_scripts(&mut self, content: &Content) -> Node {
+    fn visit_scripts(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_scripts();
 
         let _body = elem.body();
 
-        let body = _body.accept(self).into_array();
+        let body = _body.accept(self)?.into_array();
 
         let node = katex::OpBuilder::default()
             .mode(katex::Mode::Math)
@@ -368,10 +1091,10 @@ impl ContentVisitor for ContentConverter<'_> {
             .symbol(false)
             .body(Some(body))
             .build().unwrap().into_node();
-        Node::Node(node)
+        Ok(Node::Node(node))
     }
 
-    fn visit_mid(&mut self, content: &Content) -> Node {
+    fn visit_mid(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_mid();
 
         let _body = elem.body();
@@ -380,10 +1103,10 @@ impl ContentVisitor for ContentConverter<'_> {
         let node = katex::MiddleBuilder::default()
             .delim(delim)
             .build().unwrap().into_node();
-        Node::Node(node)
+        Ok(Node::Node(node))
     }
 
-    fn visit_overbrace(&mut self, content: &Content) -> Node {
+    fn visit_overbrace(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_overbrace();
 
         let _body = elem.body();
@@ -392,122 +1115,240 @@ impl ContentVisitor for ContentConverter<'_> {
         let base = katex::HorizBraceBuilder::default()
             .label("\\overbrace".to_string())
             .is_over(true)
-            .base(Box::new(_body.accept(self).into_ordgroup(katex::Mode::Math).into_node()))
+            .base(Box::new(_body.accept(self)?.into_ordgroup(katex::Mode::Math).into_node()))
             .build().unwrap().into_node();
-        let sup = _annotation.map(|c| c.accept(self).into_ordgroup(katex::Mode::Math).into_node());
+        let sup = _annotation.map(|c| c.accept(self)).transpose()?.map(|n| n.into_ordgroup(katex::Mode::Math).into_node());
 
         let node = katex::SupSubBuilder::default()
             .base(Box::new(base))
             .sup(sup.map(Box::new))
             .build().unwrap().into_node();
-        Node::Node(node)
+        Ok(Node::Node(node))
     }
 
-    fn visit_overline(&mut self, content: &Content) -> Node {
+    fn visit_overline(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_overline();
 
         let _body = elem.body();
 
-        let body = _body.accept(self).into_ordgroup(katex::Mode::Math).into_node();
+        let body = _body.accept(self)?.into_ordgroup(katex::Mode::Math).into_node();
         let node = katex::OverlineBuilder::default()
             .body(Box::new(body))
             .build().unwrap().into_node();
-        Node::Node(node)
+        Ok(Node::Node(node))
     }
 
-    fn visit_root(&mut self, content: &Content) -> Node {
+    fn visit_root(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_root();
 
         let _index = elem.index(self.styles);
         let _radicand = elem.radicand();
 
-        let index = _index.map(|c| c.accept(self).into_ordgroup(katex::Mode::Math).into_node());
-        let body = _radicand.accept(self).into_ordgroup(katex::Mode::Math).into_node();
+        let index = _index.map(|c| c.accept(self)).transpose()?.map(|n| n.into_ordgroup(katex::Mode::Math).into_node());
+        let body = _radicand.accept(self)?.into_ordgroup(katex::Mode::Math).into_node();
 
         let node = katex::SqrtBuilder::default()
             .body(Box::new(body))
             .index(index.map(Box::new))
             .build().unwrap().into_node();
-        Node::Node(node)
+        Ok(Node::Node(node))
     }
 
-    fn visit_underbrace(&mut self, content: &Content) -> Node {
+    fn visit_underbrace(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_underbrace();
 
         let _body = elem.body();
         let _annotation = elem.annotation(self.styles);
 
         let base = katex::HorizBraceBuilder::default()
-            .base(Box::new(_body.accept(self).into_ordgroup(katex::Mode::Math).into_node()))
+            .base(Box::new(_body.accept(self)?.into_ordgroup(katex::Mode::Math).into_node()))
             .is_over(false)
             .label("\\underbrace".to_string())
             .build().unwrap().into_node();
-        let sub = _annotation.map(|c| c.accept(self).into_ordgroup(katex::Mode::Math).into_node());
+        let sub = _annotation.map(|c| c.accept(self)).transpose()?.map(|n| n.into_ordgroup(katex::Mode::Math).into_node());
 
         let node = katex::SupSubBuilder::default()
             .base(Box::new(base))
             .sub(sub.map(Box::new))
             .build().unwrap().into_node();
-        Node::Node(node)
+        Ok(Node::Node(node))
     }
 
-    fn visit_underline(&mut self, content: &Content) -> Node {
+    fn visit_underline(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_underline();
 
         let _body = elem.body();
 
-        let body = _body.accept(self).into_ordgroup(katex::Mode::Math).into_node();
+        let body = _body.accept(self)?.into_ordgroup(katex::Mode::Math).into_node();
 
         let node = katex::UnderlineBuilder::default()
             .body(Box::new(body))
             .build().unwrap().into_node();
-        Node::Node(node)
+        Ok(Node::Node(node))
     }
 
-    fn visit_h(&mut self, content: &Content) -> Node {
+    fn visit_h(&mut self, content: &Content) -> ConvertResult<Node> {
         let elem = content.to_h();
 
         let _amount = elem.amount();
-        let _weak = elem.weak(self.styles); // unsupported
-
-        let length = match _amount {
-            typst::layout::Spacing::Fr(fr) => unimplemented!(),
-            typst::layout::Spacing::Rel(rel) => rel.abs,
+        let _weak = elem.weak(self.styles); // only removed at the inner edges of `lr`
+
+        // KaTeX cannot fill the remaining space of a line, so fractional
+        // spacing gets a fixed width instead.
+        let dimension = match _amount {
+            typst::layout::Spacing::Fr(fr) => {
+                let message = format!("Fractional spacing is approximated by {}em per fraction.", FR_WIDTH);
+                self.warn(content, ConvertWarning::new("h", message));
+                katex::Measurement {
+                    number: fr.get() as f32 * FR_WIDTH,
+                    unit: "em".to_string(),
+                }
+            },
+            typst::layout::Spacing::Rel(rel) => self.measurement(content, "amount", rel),
         };
 
         let node = katex::KernBuilder::default()
-            .dimension(katex::Measurement {
-                number: length.em.get() as f32,
-                unit: "em".to_string(),
-            })
+            .dimension(dimension)
             .mode(katex::Mode::Math)
             .build().unwrap().into_node();
-        Node::Node(node)
+        Ok(Node::Node(node))
     }
 
-    fn visit_underbracket(&mut self, content: &Content) -> Node {
-        // unsupported
-        unimplemented!()
+    fn visit_underbracket(&mut self, content: &Content) -> ConvertResult<Node> {
+        let elem = content.to_underbracket();
+
+        let _body = elem.body();
+        let _annotation = elem.annotation(self.styles);
+
+        let base = katex::HorizBraceBuilder::default()
+            .base(Box::new(_body.accept(self)?.into_ordgroup(katex::Mode::Math).into_node()))
+            .is_over(false)
+            .label("\\underbracket".to_string())
+            .build().unwrap().into_node();
+        let sub = _annotation.map(|c| c.accept(self)).transpose()?.map(|n| n.into_ordgroup(katex::Mode::Math).into_node());
+
+        let node = katex::SupSubBuilder::default()
+            .base(Box::new(base))
+            .sub(sub.map(Box::new))
+            .build().unwrap().into_node();
+        Ok(Node::Node(node))
     }
 
-    fn visit_overbracket(&mut self, content: &Content) -> Node {
-        // unsupported
-        unimplemented!()
+    fn visit_overbracket(&mut self, content: &Content) -> ConvertResult<Node> {
+        let elem = content.to_overbracket();
+
+        let _body = elem.body();
+        let _annotation = elem.annotation(self.styles);
+
+        let base = katex::HorizBraceBuilder::default()
+            .label("\\overbracket".to_string())
+            .is_over(true)
+            .base(Box::new(_body.accept(self)?.into_ordgroup(katex::Mode::Math).into_node()))
+            .build().unwrap().into_node();
+        let sup = _annotation.map(|c| c.accept(self)).transpose()?.map(|n| n.into_ordgroup(katex::Mode::Math).into_node());
+
+        let node = katex::SupSubBuilder::default()
+            .base(Box::new(base))
+            .sup(sup.map(Box::new))
+            .build().unwrap().into_node();
+        Ok(Node::Node(node))
     }
 
-    fn visit_class(&mut self, content: &Content) -> Node {
-        // unsupported
-        unimplemented!()
+    fn visit_class(&mut self, content: &Content) -> ConvertResult<Node> {
+        let elem = content.to_class();
+
+        let _class = elem.class();
+        let _body = elem.body();
+
+        let body = _body.accept(self)?.into_array();
+
+        // Large operators also take their scripts as limits, so they become an operator.
+        if _class == MathClass::Large {
+            let node = katex::OpBuilder::default()
+                .mode(katex::Mode::Math)
+                .limits(true)
+                .parent_is_sup_sub(false)
+                .symbol(false)
+                .body(Some(body))
+                .build().unwrap().into_node();
+            return Ok(Node::Node(node));
+        }
+
+        let mclass = match _class {
+            MathClass::Binary | MathClass::Vary => katex::MClassType::MBin,
+            MathClass::Relation => katex::MClassType::MRel,
+            MathClass::Opening => katex::MClassType::MOpen,
+            MathClass::Closing => katex::MClassType::MClose,
+            MathClass::Punctuation => katex::MClassType::MPunct,
+            MathClass::Large => katex::MClassType::MOp,
+            MathClass::Normal |
+            MathClass::Alphabetic |
+            MathClass::Fence |
+            MathClass::Unary |
+            MathClass::Diacritic |
+            MathClass::GlyphPart |
+            MathClass::Space |
+            MathClass::Special => katex::MClassType::MOrd,
+        };
+        let is_character_box = body.len() == 1 && matches!(body[0],
+            katex::Node::MathOrd(_) |
+            katex::Node::TextOrd(_) |
+            katex::Node::Atom(_));
+
+        let node = katex::MClassBuilder::default()
+            .mclass(mclass)
+            .body(body)
+            .is_character_box(is_character_box)
+            .build().unwrap().into_node();
+        Ok(Node::Node(node))
     }
 
-    fn visit_primes(&mut self, content: &Content) -> Node {
-        // unsupported
-        unimplemented!()
+    fn visit_primes(&mut self, content: &Content) -> ConvertResult<Node> {
+        let elem = content.to_primes();
+
+        let _count = elem.count();
+
+        Ok(symbol::primes(_count))
     }
 
-    fn visit_accent(&mut self, content: &Content) -> Node {
-        // unsupported
-        unimplemented!()
+    fn visit_accent(&mut self, content: &Content) -> ConvertResult<Node> {
+        let elem = content.to_accent();
+
+        let _base = elem.base();
+        let _accent = elem.accent();
+
+        let base = _base.accept(self)?.into_node_fallback_ordgroup(katex::Mode::Math);
+        let label = match _accent.label() {
+            Some(label) => label,
+            None => {
+                self.warn(content, ConvertWarning::new("accent", format!("Accent `{:?}` is unsupported.", _accent)));
+                return Ok(Node::Node(base));
+            }
+        };
+
+        // Typst stretches hats, tildes and carons to cover wider bases.
+        let is_character_box = matches!(base,
+            katex::Node::MathOrd(_) |
+            katex::Node::TextOrd(_) |
+            katex::Node::Atom(_));
+        let label = match label {
+            "\\hat" if !is_character_box => "\\widehat",
+            "\\tilde" if !is_character_box => "\\widetilde",
+            "\\check" if !is_character_box => "\\widecheck",
+            _ => label,
+        };
+        let is_stretchy = !matches!(label,
+            "\\acute" | "\\grave" | "\\ddot" | "\\tilde" | "\\bar" | "\\breve" |
+            "\\check" | "\\hat" | "\\vec" | "\\dot" | "\\mathring");
+        let is_shifty = !is_stretchy || matches!(label, "\\widehat" | "\\widetilde" | "\\widecheck");
+
+        let node = katex::AccentBuilder::default()
+            .label(label.to_string())
+            .is_stretchy(Some(is_stretchy))
+            .is_shifty(Some(is_shifty))
+            .base(Box::new(base))
+            .build().unwrap().into_node();
+        Ok(Node::Node(node))
     }
 }
 
@@ -528,13 +1369,13 @@ impl<'a> SequenceConverter<'a> {
         }
     }
 
-    pub fn convert(&mut self, visitor: &mut ContentConverter) -> Node {
-        self.process_sequence_elements(visitor);
+    pub fn convert(&mut self, visitor: &mut ContentConverter) -> ConvertResult<Node> {
+        self.process_sequence_elements(visitor)?;
 
         if self.is_aligned {
-            self.convert_align()
+            Ok(self.convert_align(visitor))
         } else {
-            self.convert_flatten()
+            Ok(self.convert_flatten())
         }
     }
 
@@ -543,7 +1384,7 @@ impl<'a> SequenceConverter<'a> {
         Node::Array(nodes.collect())
     }
 
-    pub fn convert_align(&mut self) -> Node {
+    pub fn convert_align(&mut self, visitor: &ContentConverter) -> Node {
         let mut constructor = katex::ArrayConstructor::default();
 
         for row in self.body.iter_mut() {
@@ -553,7 +1394,7 @@ impl<'a> SequenceConverter<'a> {
                     .body(node.clone().into_array())
                     .build().unwrap().into_node();
                 let styling = katex::StylingBuilder::default()
-                    .style(katex::StyleStr::Display)
+                    .style(visitor.style())
                     .body([ordgroup].to_vec())
                     .build().unwrap().into_node();
                 constructor.push_node(styling)
@@ -563,14 +1404,15 @@ impl<'a> SequenceConverter<'a> {
 
         let array = constructor.builder()
             .add_jot(true)
-            .leqno(false)
+            .leqno(visitor.settings.leqno)
             .col_separation_type(katex::ColSeparationType::Align)
             .build().unwrap().into_node();
         Node::Node(array)
     }
 
-    pub fn process_sequence_elements(&mut self, visitor: &mut ContentConverter) {
-        let sequence = self.content.to_sequence().unwrap();
+    pub fn process_sequence_elements(&mut self, visitor: &mut ContentConverter) -> ConvertResult<()> {
+        let sequence = self.content.to_sequence()
+            .ok_or_else(|| ConvertError::MalformedContent("Expected a sequence.".to_string()))?;
 
         for (i, elem) in sequence.enumerate() {
             if elem.is_linebreak() || elem.is_align_point() {
@@ -584,10 +1426,11 @@ impl<'a> SequenceConverter<'a> {
             }
             visitor.parent = Some(self.content.clone());
             visitor.position = Some(i);
-            let node = elem.accept(visitor);
+            let node = elem.accept(visitor)?;
             self.stack.push(node);
         }
         self.dump_stack_onto_body();
+        Ok(())
     }
 
     pub fn dump_stack_onto_body(&mut self) {
@@ -601,22 +1444,24 @@ impl<'a> SequenceConverter<'a> {
 }
 
 pub struct CasesConverter<'a> {
+    pub content: &'a Content,
     pub elem: &'a typst::math::CasesElem,
     pub body: Vec<Vec<Node>>,
     pub stack: Vec<Node>,
 }
 
 impl<'a> CasesConverter<'a> {
-    pub fn new(elem: &'a typst::math::CasesElem) -> Self {
+    pub fn new(content: &'a Content) -> Self {
         Self {
-            elem,
+            content,
+            elem: content.to_cases(),
             body: Vec::new(),
             stack: Vec::new(),
         }
     }
 
-    pub fn convert(&mut self, visitor: &mut ContentConverter) -> Node {
-        self.process_children(visitor);
+    pub fn convert(&mut self, visitor: &mut ContentConverter) -> ConvertResult<Node> {
+        self.process_children(visitor)?;
 
         let mut constructor = katex::ArrayConstructor::default();
         for row in self.body.iter_mut() {
@@ -645,51 +1490,57 @@ impl<'a> CasesConverter<'a> {
                 postgap: Some(0f32),
             }),
         ];
-        let array = constructor.builder()
+        let mut builder = constructor.builder();
+        let _gap = self.elem.gap(visitor.styles);
+        if let Some(row_gaps) = visitor.row_gaps(self.content, "gap", _gap, constructor.count_rows()) {
+            builder.row_gaps(row_gaps);
+        }
+        let array = builder
             .arraystretch(1.2)
             .cols(cols)
             .build().unwrap().into_node();
-        let leftright = katex::LeftRightBuilder::default()
-            .body([array].to_vec())
-            .left("\\{".to_string())
-            .right(".".to_string())
-            .build().unwrap().into_node();
-
-        Node::Node(leftright)
+        let delim = self.elem.delim(visitor.styles);
+        let node = match self.elem.reverse(visitor.styles) {
+            true => delimited(array, None, Some(delim.close())),
+            false => delimited(array, Some(delim.open()), None),
+        };
+        Ok(node)
     }
 
-    pub fn process_children(&mut self, visitor: &mut ContentConverter) {
+    pub fn process_children(&mut self, visitor: &mut ContentConverter) -> ConvertResult<()> {
         for child in self.elem.children() {
             if child.is_sequence() {
                 let mut converter = SequenceConverter::new(child);
-                converter.process_sequence_elements(visitor);
+                converter.process_sequence_elements(visitor)?;
                 self.body.extend(converter.body);
             } else {
-                self.body.push([child.accept(visitor)].to_vec());
+                self.body.push([child.accept(visitor)?].to_vec());
             }
         }
+        Ok(())
     }
 }
 
 pub struct VecConverter<'a> {
+    pub content: &'a Content,
     pub elem: &'a typst::math::VecElem,
 }
 
 impl<'a> VecConverter<'a> {
-    pub fn new(elem: &'a typst::math::VecElem) -> Self {
+    pub fn new(content: &'a Content) -> Self {
         Self {
-            elem,
+            content,
+            elem: content.to_vec(),
         }
     }
 
-    pub fn convert(&mut self, visitor: &mut ContentConverter) -> Node {
+    pub fn convert(&mut self, visitor: &mut ContentConverter) -> ConvertResult<Node> {
         let mut constructor = katex::ArrayConstructor::default();
 
         for content in self.elem.children() {
             constructor.next_row();
-            let node = content.accept(visitor).into_node().unwrap();
             let ordgroup = katex::OrdGroupBuilder::default()
-                .body([node].to_vec())
+                .body(content.accept(visitor)?.into_array())
                 .build().unwrap().into_node();
             let styling = katex::StylingBuilder::default()
                 .body([ordgroup].to_vec())
@@ -699,43 +1550,77 @@ impl<'a> VecConverter<'a> {
         }
         constructor.cols_center_align();
 
+        let _gap = self.elem.gap(visitor.styles);
+        let row_gaps = visitor.row_gaps(self.content, "gap", _gap, constructor.count_rows());
+
         let mut builder = constructor.builder();
         let array = builder
             .hskip_before_and_after(false)
-            .row_gaps([None].to_vec())
-            .build().unwrap().into_node();
-        let delim = self.elem.delim(visitor.styles).unwrap();
-        let leftright = katex::LeftRightBuilder::default()
-            .body([array].to_vec())
-            .left(delim.open().to_string())
-            .right(delim.close().to_string())
+            .row_gaps(row_gaps.unwrap_or([None].to_vec()))
             .build().unwrap().into_node();
-        Node::Node(leftright)
+        let delim = self.elem.delim(visitor.styles);
+        Ok(delimited(array, delim.map(|d| d.open()), delim.map(|d| d.close())))
     }
 }
 
 pub struct TextConverter<'a> {
+    pub content: &'a Content,
     pub elem: &'a typst::text::TextElem,
 }
 
 impl<'a> TextConverter<'a> {
-    pub fn new(elem: &'a typst::text::TextElem) -> Self {
+    pub fn new(content: &'a Content) -> Self {
         Self {
-            elem,
+            content,
+            elem: content.to_text(),
         }
     }
 
-    pub fn convert(&mut self) -> Node {
+    /// Converts the text the way Typst lays it out: single characters and
+    /// numbers as math symbols, strings as upright text, and other words as
+    /// upright identifiers.
+    pub fn convert(&mut self, visitor: &ContentConverter) -> Node {
         let text = self.elem.text();
         if text.chars().count() == 1 {
             let name = text.chars().next().unwrap();
             self.convert_char(name, katex::Mode::Math)
+        } else if Self::is_number(text) {
+            Self::convert_number(text)
+        } else if visitor.is_quoted(self.content) {
+            Self::convert_text(text)
         } else {
-            self.convert_text(text)
+            Self::convert_identifier(text, visitor)
+        }
+    }
+
+    /// Whether the text is a number, like `123.45`, which Typst lays out as
+    /// a single group of digits.
+    pub fn is_number(text: &str) -> bool {
+        text.chars().all(|c| c.is_ascii_digit() || c == '.')
+    }
+
+    pub fn convert_number(text: &str) -> Node {
+        let body = text.chars().map(|name| katex::Symbol::get(katex::Mode::Math, name).create_node()).collect();
+        Node::Array(body)
+    }
+
+    pub fn convert_identifier(text: &str, visitor: &ContentConverter) -> Node {
+        let body = text.chars().map(|name| katex::Symbol::get(katex::Mode::Math, name).create_node()).collect();
+        let body = Node::Array(body);
+
+        // Identifiers are upright, unless an enclosing math style sets the font.
+        let style = visitor.font_style;
+        if style.variant.is_some() || style.bold.is_some() || style.italic.is_some() {
+            return body;
         }
+        let node = katex::FontBuilder::default()
+            .body(Box::new(body.into_node_fallback_ordgroup(katex::Mode::Math)))
+            .font("mathrm".to_string())
+            .build().unwrap().into_node();
+        Node::Node(node)
     }
 
-    pub fn convert_text(&mut self, text: &str) -> Node {
+    pub fn convert_text(text: &str) -> Node {
         let body = text.chars().map(|name| katex::Symbol::get(katex::Mode::Text, name).create_node()).collect();
 
         let text = katex::TextBuilder::default()
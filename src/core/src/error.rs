@@ -0,0 +1,87 @@
+use std::fmt;
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::katex::SourceLocation;
+
+pub type ConvertResult<T> = Result<T, ConvertError>;
+
+/// An error that aborts the conversion of a Typst content tree.
+#[derive(Debug, Clone)]
+pub enum ConvertError {
+    /// Typst could not parse or evaluate the expression.
+    Eval(String),
+    /// The element has no KaTeX counterpart.
+    UnsupportedElement(String),
+    /// The content is not structured the way the element expects it to be.
+    MalformedContent(String),
+    /// The content is nested deeper than the settings allow.
+    TooDeeplyNested(usize),
+    /// A warning raised in strict mode.
+    Strict(String),
+}
+
+impl ConvertError {
+    /// A name for the kind of error, exposed to JavaScript.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Eval(_) => "Eval",
+            Self::UnsupportedElement(_) => "UnsupportedElement",
+            Self::MalformedContent(_) => "MalformedContent",
+            Self::TooDeeplyNested(_) => "TooDeeplyNested",
+            Self::Strict(_) => "Strict",
+        }
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Eval(message) => write!(f, "{}", message),
+            Self::UnsupportedElement(element) => {
+                write!(f, "Element `{}` is unsupported.", element)
+            },
+            Self::MalformedContent(message) => write!(f, "{}", message),
+            Self::TooDeeplyNested(max) => {
+                write!(f, "Content is nested deeper than {} levels.", max)
+            },
+            Self::Strict(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+impl From<ConvertError> for JsValue {
+    fn from(err: ConvertError) -> Self {
+        let error = js_sys::Error::new(&err.to_string());
+        error.set_name("ConvertError");
+        let _ = js_sys::Reflect::set(&error, &"kind".into(), &err.kind().into());
+        error.into()
+    }
+}
+
+/// A problem that does not abort the conversion, but makes its output differ
+/// from what Typst would produce.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConvertWarning {
+    pub element: String,
+    pub message: String,
+    pub loc: Option<SourceLocation>,
+}
+
+impl ConvertWarning {
+    pub fn new(element: &str, message: String) -> Self {
+        Self {
+            element: element.to_string(),
+            message,
+            loc: None,
+        }
+    }
+
+    pub fn unsupported_argument(element: &str, argument: &str) -> Self {
+        let message = format!("Argument `{}` of element `{}` is unsupported.", argument, element);
+        Self::new(element, message)
+    }
+}
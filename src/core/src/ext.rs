@@ -1,4 +1,7 @@
 use typst;
+use typst::foundations::IntoValue;
+
+use crate::error::{ConvertError, ConvertResult};
 
 pub trait DelimiterOpenClose {
     fn open(self) -> char;
@@ -28,3 +31,59 @@ impl DelimiterOpenClose for typst::math::Delimiter {
         }
     }
 }
+
+pub trait CssColor {
+    fn css(self) -> String;
+}
+
+impl CssColor for typst::visualize::Color {
+    /// The color as a CSS hex string, which KaTeX accepts wherever it takes a
+    /// color. Colors of other spaces, like luma or CMYK, are converted to RGB.
+    fn css(self) -> String {
+        self.to_hex().to_string()
+    }
+}
+
+pub trait AugmentLines {
+    fn lines(self) -> ConvertResult<Vec<isize>>;
+}
+
+impl AugmentLines for typst::math::AugmentOffsets {
+    /// The offsets of the lines, which may be negative to count from the end.
+    fn lines(self) -> ConvertResult<Vec<isize>> {
+        self.into_value()
+            .cast()
+            .map_err(|err| ConvertError::MalformedContent(format!("Cannot read the augmentation lines: {}", err)))
+    }
+}
+
+pub trait AccentLabel {
+    fn label(self) -> Option<&'static str>;
+}
+
+impl AccentLabel for typst::math::Accent {
+    /// The KaTeX command corresponding to the accent.
+    fn label(self) -> Option<&'static str> {
+        let c = self.into_value().cast::<char>().ok()?;
+        let label = match c {
+            '\u{0300}' | '`' => "\\grave",
+            '\u{0301}' | '´' => "\\acute",
+            '\u{0302}' | '^' | 'ˆ' => "\\hat",
+            '\u{0303}' | '~' | '∼' | '˜' => "\\tilde",
+            '\u{0304}' | '¯' => "\\bar",
+            '\u{0305}' | '-' | '‾' | '−' => "\\bar",
+            '\u{0306}' | '˘' => "\\breve",
+            '\u{0307}' | '.' | '˙' | '⋅' => "\\dot",
+            '\u{0308}' | '¨' => "\\ddot",
+            '\u{030a}' | '∘' | '○' => "\\mathring",
+            '\u{030c}' | 'ˇ' => "\\check",
+            '\u{20d6}' | '←' => "\\overleftarrow",
+            '\u{20d7}' | '→' | '⟶' => "\\overrightarrow",
+            '\u{20e1}' | '↔' | '⟷' => "\\overleftrightarrow",
+            '\u{20d0}' | '↼' => "\\overleftharpoon",
+            '\u{20d1}' | '⇀' => "\\overrightharpoon",
+            _ => return None,
+        };
+        Some(label)
+    }
+}
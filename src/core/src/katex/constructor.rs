@@ -68,6 +68,55 @@ impl ArrayConstructor {
         self
     }
 
+    /// Sets the space on either side of the boundaries between columns, in em.
+    /// The outer edges of the first and last columns are not padded.
+    pub fn cols_sep(&mut self, sep: f32) -> &mut Self {
+        if self.cols.is_none() {
+            self.cols_center_align();
+        }
+        let cols = self.cols.as_mut().unwrap();
+        let count = cols.iter().filter(|spec| matches!(spec, AlignSpec::Align(_))).count();
+        let aligns = cols.iter_mut().filter_map(|spec| match spec {
+            AlignSpec::Align(align) => Some(align),
+            _ => None,
+        });
+        for (i, align) in aligns.enumerate() {
+            align.pregap = Some(if i == 0 { 0.0 } else { sep });
+            align.postgap = Some(if i + 1 == count { 0.0 } else { sep });
+        }
+        self
+    }
+
+    /// Draws a vertical line after the column with the given index.
+    pub fn vline_after_column(&mut self, column: usize) -> &mut Self {
+        if self.cols.is_none() {
+            self.cols_center_align();
+        }
+        let cols = self.cols.as_mut().unwrap();
+
+        let mut count = 0;
+        let position = cols.iter().position(|spec| {
+            if let AlignSpec::Align(_) = spec {
+                count += 1;
+            }
+            count > column
+        });
+        let separator = AlignSpec::Separator(Separator {
+            separator: "|".to_string(),
+        });
+        match position {
+            Some(position) => cols.insert(position + 1, separator),
+            None => cols.push(separator),
+        }
+        self
+    }
+
+    /// Draws a horizontal line before the row with the given index.
+    pub fn hline_before_row(&mut self, row: usize) -> &mut Self {
+        self.h_lines_before_row[row].push(false);
+        self
+    }
+
     pub fn builder(&self) -> ArrayBuilder {
         ArrayBuilder::default()
             .body(self.body.clone())
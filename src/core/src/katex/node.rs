@@ -323,6 +323,7 @@ pub struct OpToken {
 }
 
 #[derive(Clone, Serialize, Builder)]
+#[serde(rename_all = "camelCase")]
 pub struct Accent {
     #[builder(default = "Mode::Math")]
     pub mode: Mode,
@@ -337,6 +338,7 @@ pub struct Accent {
 }
 
 #[derive(Clone, Serialize, Builder)]
+#[serde(rename_all = "camelCase")]
 pub struct AccentUnder {
     #[builder(default = "Mode::Math")]
     pub mode: Mode,
@@ -374,6 +376,7 @@ pub struct DelimSizing {
 }
 
 #[derive(Clone, Serialize, Builder)]
+#[serde(rename_all = "camelCase")]
 pub struct Enclose {
     #[builder(default = "Mode::Math")]
     pub mode: Mode,
@@ -579,12 +582,13 @@ pub struct Middle {
 }
 
 #[derive(Clone, Serialize, Builder)]
+#[serde(rename_all = "camelCase")]
 pub struct MClass {
     #[builder(default = "Mode::Math")]
     pub mode: Mode,
     #[builder(default)]
     pub loc: Option<SourceLocation>,
-    pub mclass: String,
+    pub mclass: MClassType,
     pub body: NodeArray,
     pub is_character_box: bool,
 }
@@ -740,6 +744,14 @@ macro_rules! into_node {
                 }
             }
         )*
+
+        impl Node {
+            pub fn loc_mut(&mut self) -> &mut Option<SourceLocation> {
+                match self {
+                    $(Node::$t(node) => &mut node.loc,)*
+                }
+            }
+        }
     };
 }
 
@@ -2,7 +2,10 @@
 
 use serde::Serialize;
 
-#[derive(Clone, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SourceLocation {
-
+    /// Start offset, zero-based inclusive.
+    pub start: usize,
+    /// End offset, zero-based exclusive.
+    pub end: usize,
 }
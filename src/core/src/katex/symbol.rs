@@ -58,6 +58,12 @@ impl<'a> Symbol {
         if name == "}" {
             name = "\\}".to_string();
         }
+        if name == "‖" {
+            name = match self.mode {
+                Mode::Math => "\\Vert".to_string(),
+                Mode::Text => "\\textbardbl".to_string(),
+            };
+        }
 
         match self.group {
             Group::Atom(group) => {
@@ -90,8 +96,18 @@ impl<'a> Symbol {
                             .text(name)
                             .build().unwrap().into_node()
                     },
-                    NonAtomGroup::AccentToken => unimplemented!(),
-                    NonAtomGroup::Spacing => unimplemented!(),
+                    NonAtomGroup::AccentToken => {
+                        AccentTokenBuilder::default()
+                            .mode(self.mode)
+                            .text(name)
+                            .build().unwrap().into_node()
+                    },
+                    NonAtomGroup::Spacing => {
+                        SpacingBuilder::default()
+                            .mode(self.mode)
+                            .text(name)
+                            .build().unwrap().into_node()
+                    },
                 }
             }
         }
@@ -103,6 +119,10 @@ impl<'a> Symbol {
         if math_text.contains(name) && mode == Mode::Math {
             return Symbol::new(Mode::Math, Font::Main, Group::NonAtom(NonAtomGroup::TextOrd), name);
         }
+        // Fences are ordinary symbols, and only become delimiters inside `lr`.
+        if "|‖".contains(name) {
+            return Symbol::new(mode, Font::Main, Group::NonAtom(NonAtomGroup::TextOrd), name);
+        }
         //// --- AUTO GENERATED CODE --- ////
         if mode == Mode::Math && name == '≡' { return Symbol::new(Mode::Math, Font::Main, Group::Atom(AtomGroup::Rel), '≡'); }
         if mode == Mode::Math && name == '≺' { return Symbol::new(Mode::Math, Font::Main, Group::Atom(AtomGroup::Rel), '≺'); }
@@ -755,3 +775,23 @@ impl<'a> Symbol {
         return Symbol::new(mode, Font::Main, Group::NonAtom(NonAtomGroup::TextOrd), name);
     }
 }
+
+/// The symbols KaTeX accepts as delimiters.
+/// Reference: delimsizing.js
+pub const DELIMITERS: [&str; 53] = [
+    "(", "\\lparen", ")", "\\rparen",
+    "[", "\\lbrack", "]", "\\rbrack",
+    "\\{", "\\lbrace", "\\}", "\\rbrace",
+    "\\lfloor", "\\rfloor", "\u{230a}", "\u{230b}",
+    "\\lceil", "\\rceil", "\u{2308}", "\u{2309}",
+    "<", ">", "\\langle", "\u{27e8}", "\\rangle", "\u{27e9}", "\\lt", "\\gt",
+    "\\lvert", "\\rvert", "\\lVert", "\\rVert",
+    "\\lgroup", "\\rgroup", "\u{27ee}", "\u{27ef}",
+    "\\lmoustache", "\\rmoustache", "\u{23b0}", "\u{23b1}",
+    "/", "\\backslash",
+    "|", "\\vert", "\\|", "\\Vert",
+    "\\uparrow", "\\Uparrow",
+    "\\downarrow", "\\Downarrow",
+    "\\updownarrow", "\\Updownarrow",
+    ".",
+];
@@ -65,20 +65,31 @@ pub enum StyleStr {
     ScriptScript,
 }
 
-#[derive(Clone, Serialize)]
-pub enum SizeType { // TODO: Check serialization
+#[derive(Clone)]
+pub enum SizeType {
     One = 1,
     Two = 2,
     Three = 3,
     Four = 4,
 }
 
+impl Serialize for SizeType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.clone() as u8)
+    }
+}
+
 #[derive(Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum MClassType {
+    MOrd,
+    MOp,
+    MBin,
+    MRel,
     MOpen,
     MClose,
-    MRel,
-    MOrd,
+    MPunct,
+    MInner,
 }
 
 #[derive(Clone, Serialize)]
@@ -1,5 +1,6 @@
 use wasm_bindgen::prelude::*;
-use serde_wasm_bindgen::to_value;
+use serde::Serialize;
+use serde_wasm_bindgen::from_value;
 use serde_json;
 use typst;
 
@@ -10,33 +11,97 @@ mod node;
 mod ext;
 mod content;
 mod symbol;
+pub mod error;
+mod settings;
+mod reference;
 
-fn content_tree(expression: &str) -> Result<typst::foundations::Content, String> {
+fn content_tree(expression: &str) -> Result<(typst::foundations::Content, typst::syntax::Source), error::ConvertError> {
     let world = utils::FakeWorld::new();
     utils::eval(&world, expression)
 }
 
-pub fn convert(content: &typst::foundations::Content) -> serde_json::Value {
-    let katex_tree = converter::convert(content);
-    serde_json::to_value(&katex_tree).unwrap()
+/// Converts a value to JavaScript. Maps become plain objects, which is what
+/// KaTeX expects of node attributes.
+fn to_value<T: Serialize>(value: &T) -> Result<JsValue, serde_wasm_bindgen::Error> {
+    let serializer = serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+    value.serialize(&serializer)
+}
+
+fn parse_settings(settings: JsValue) -> Result<settings::Settings, JsValue> {
+    if settings.is_undefined() || settings.is_null() {
+        return Ok(settings::Settings::default());
+    }
+    Ok(from_value(settings)?)
+}
+
+fn convert_expression(
+    expression: &str,
+    settings: &settings::Settings,
+) -> Result<converter::Conversion, JsValue> {
+    let (content, source) = content_tree(expression)?;
+    let offset = utils::trim_offset(expression);
+    Ok(converter::convert_with_diagnostics(&content, Some(&source), offset, settings)?)
+}
+
+pub fn convert(content: &typst::foundations::Content) -> Result<serde_json::Value, error::ConvertError> {
+    let katex_tree = converter::convert(content, None, 0, &settings::Settings::default())?;
+    Ok(serde_json::to_value(&katex_tree).unwrap())
 }
 
 #[wasm_bindgen(js_name = "parseTree")]
-pub fn parse_tree(expression: &str) -> Result<JsValue, String> {
+pub fn parse_tree(expression: &str, settings: JsValue) -> Result<JsValue, JsValue> {
     #[cfg(debug_assertions)]
     console_error_panic_hook::set_once();
-    let content = content_tree(expression);
-    content.map(|c| {
-        let katex_tree = converter::convert(&c);
-        to_value(&katex_tree).unwrap()
-    })
+    let settings = parse_settings(settings)?;
+    let katex_tree = match convert_expression(expression, &settings) {
+        Err(_) if !settings.throw_on_error => converter::convert_error(expression.trim(), &settings),
+        result => result?.tree,
+    };
+    Ok(to_value(&katex_tree)?)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TreeWithDiagnostics {
+    tree: node::Node,
+    warnings: Vec<error::ConvertWarning>,
+    equation_counter: Vec<usize>,
+    labels: Vec<reference::EquationLabel>,
+}
+
+#[wasm_bindgen(js_name = "parseTreeWithDiagnostics")]
+pub fn parse_tree_with_diagnostics(expression: &str, settings: JsValue) -> Result<JsValue, JsValue> {
+    #[cfg(debug_assertions)]
+    console_error_panic_hook::set_once();
+    let settings = parse_settings(settings)?;
+    let conversion = match convert_expression(expression, &settings) {
+        Err(_) if !settings.throw_on_error => converter::Conversion {
+            tree: converter::convert_error(expression.trim(), &settings),
+            warnings: Vec::new(),
+            equation_counter: settings.equation_counter.clone(),
+            labels: Vec::new(),
+        },
+        result => result?,
+    };
+    Ok(to_value(&TreeWithDiagnostics {
+        tree: conversion.tree,
+        warnings: conversion.warnings,
+        equation_counter: conversion.equation_counter,
+        labels: conversion.labels,
+    })?)
+}
+
+#[wasm_bindgen(js_name = "resolveReference")]
+pub fn resolve_reference(reference: &str, labels: JsValue) -> Result<Option<String>, JsValue> {
+    let labels: Vec<reference::EquationLabel> = from_value(labels)?;
+    Ok(reference::resolve(reference, &labels))
 }
 
 #[wasm_bindgen(js_name = "typstContentTree")]
-pub fn typst_content_tree(expression: &str) -> Result<String, String> {
+pub fn typst_content_tree(expression: &str) -> Result<String, JsValue> {
     let content = content_tree(expression);
     match content {
-        Ok(tree) => Ok(format!("{:#?}", tree).into()),
-        Err(err) => Err(err),
+        Ok((tree, _)) => Ok(format!("{:#?}", tree).into()),
+        Err(err) => Err(err.into()),
     }
 }
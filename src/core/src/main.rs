@@ -92,22 +92,22 @@ fn eval(world: &dyn World, string: &str) -> typst::foundations::Content {
 // bb(N) = NN
 // f: NN -> RR
 // vec(a, b, c) dot vec(1, 2, 3) = a + 2b + 3c
+// grave(a) = accent(a, `)
+// arrow(a) = accent(a, arrow)
+// tilde(a) = accent(a, \u{0303})
+// attach(Pi, t: alpha, b: beta, tl: 1, tr: 2+3, bl: 4+5, br: 6)
+// x class("relation", ~) y
 
 // Works partially
-// attach(Pi, t: alpha, b: beta, tl: 1, tr: 2+3, bl: 4+5, br: 6)
 // lr(]sum_(x=1)^n] x, size: #50%)
 // mat(1, 2, ..., 10; 2, 2, ..., 10; dots.v, dots.v, dots.down, dots.v; 10, 10, ..., 10)
 // upright(A) != A
 
 // Does not work
-// grave(a) = accent(a, `)
-// arrow(a) = accent(a, arrow)
-// tilde(a) = accent(a, \u{0303})
 // scripts(sum)_1^2 != sum_1^2
 // limits(A)_1^2 != A_1^2
 // (a dot b dot cancel(x)) / cancel(x)
 // f(x, y) := cases(1 "if" (x dot y)/2 <= 0, 2 "if" x "is even", 3 "if" x in NN, 4 "else")
-// Class: https://typst.app/docs/reference/math/class/
 // abs((x + y) / 2)
 // { x mid(|) sum_(i=1)^n w_i|f_i (x)| < 1 }
 // norm(x/2)
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/// A labelled equation, which references can be resolved against.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EquationLabel {
+    /// The name of the label, without angle brackets.
+    pub label: String,
+    /// The number of the equation, if it is numbered.
+    pub number: Option<String>,
+    /// The text put before the number in references.
+    pub supplement: Option<String>,
+}
+
+/// Resolves a reference like `@eq:energy` to the text Typst shows for it, like
+/// `Equation (1)`. Equations without numbers cannot be referenced.
+pub fn resolve(reference: &str, labels: &[EquationLabel]) -> Option<String> {
+    let name = reference.strip_prefix('@').unwrap_or(reference);
+    let label = labels.iter().find(|label| label.label == name)?;
+    let number = label.number.as_ref()?;
+    match &label.supplement {
+        Some(supplement) if !supplement.is_empty() => Some(format!("{} {}", supplement, number)),
+        _ => Some(number.clone()),
+    }
+}
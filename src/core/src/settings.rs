@@ -0,0 +1,89 @@
+// Reference: Settings.js
+
+use serde::{Deserialize, Deserializer};
+
+/// How to handle constructs that cannot be converted faithfully.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Strict {
+    /// Drop the unsupported constructs, reporting them only with the tree.
+    Ignore,
+    /// Drop the unsupported constructs, reporting them with the tree and in
+    /// the log.
+    Warn,
+    /// Fail the conversion.
+    Error,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StrictValue {
+    Bool(bool),
+    String(String),
+    Other(serde::de::IgnoredAny),
+}
+
+impl From<StrictValue> for Strict {
+    fn from(value: StrictValue) -> Self {
+        match value {
+            StrictValue::Bool(true) => Self::Error,
+            StrictValue::Bool(false) => Self::Ignore,
+            StrictValue::String(s) if s == "error" => Self::Error,
+            StrictValue::String(s) if s == "ignore" => Self::Ignore,
+            _ => Self::Warn,
+        }
+    }
+}
+
+/// The render settings passed from JavaScript, mirroring KaTeX's options.
+#[derive(Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Settings {
+    pub display_mode: bool,
+    pub leqno: bool,
+    pub throw_on_error: bool,
+    pub error_color: String,
+    #[serde(deserialize_with = "deserialize_strict")]
+    pub strict: Strict,
+    /// Maximum nesting depth of the converted content. Like in KaTeX, it may
+    /// be `Infinity`.
+    #[serde(deserialize_with = "deserialize_max_expand")]
+    pub max_expand: usize,
+    /// Numbering pattern of display equations, like `(1)` or `(1.a)`.
+    pub numbering: Option<String>,
+    /// Value of the equation counter before the expression. Its last number
+    /// is stepped for every numbered equation or line.
+    pub equation_counter: Vec<usize>,
+    /// Label of the equation, like `eq:energy`, which references resolve
+    /// against. Math has no syntax for labels, so it is given here.
+    pub label: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            display_mode: false,
+            leqno: false,
+            throw_on_error: true,
+            error_color: "#cc0000".to_string(),
+            strict: Strict::Warn,
+            max_expand: 1000,
+            numbering: None,
+            equation_counter: Vec::new(),
+            label: None,
+        }
+    }
+}
+
+fn deserialize_max_expand<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    let value = f64::deserialize(deserializer)?;
+    if value.is_nan() || value <= 0.0 {
+        return Ok(0);
+    }
+    // Casting saturates, so `Infinity` lifts the limit.
+    Ok(value as usize)
+}
+
+fn deserialize_strict<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Strict, D::Error> {
+    // KaTeX also takes a function, which cannot be read and falls back to warnings.
+    Ok(StrictValue::deserialize(deserializer).map_or(Strict::Warn, Strict::from))
+}
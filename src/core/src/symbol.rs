@@ -17,7 +17,7 @@ pub fn equals() -> Node {
 
 pub fn neq() -> Node {
     let not = MClassBuilder::default()
-        .mclass("rel".to_string())
+        .mclass(katex::MClassType::MRel)
         .body([
             LapBuilder::default()
                 .alignment("rlap".to_string())
@@ -27,7 +27,7 @@ pub fn neq() -> Node {
         .is_character_box(false)
         .build().unwrap().into_node();
     Node::Node(MClassBuilder::default()
-        .mclass("mrel".to_string())
+        .mclass(katex::MClassType::MRel)
         .is_character_box(false)
         .body([not, equals().into_node().unwrap()].to_vec())
         .build().unwrap().into_node())
@@ -39,3 +39,45 @@ pub fn define() -> Node {
         katex::Symbol::get(katex::Mode::Math, '=').create_node(),
     ].to_vec())
 }
+
+/// KaTeX fonts only have the single prime glyph, so `″`, `‴` and `⁗` are
+/// composed out of single primes, just like KaTeX does for `f''`.
+pub fn primes(count: usize) -> Node {
+    let prime = katex::TextOrdBuilder::default()
+        .text("\\prime".to_string())
+        .build().unwrap().into_node();
+    Node::Array(vec![prime; count])
+}
+
+/// The KaTeX delimiter a node can be used as on the left or right side, if any.
+/// Vertical bars and the delimiters of `floor`, `ceil` and `round` are named,
+/// so that KaTeX uses the opening and closing variants.
+pub fn delimiter(node: &katex::Node, left: bool) -> Option<String> {
+    let text = match node {
+        katex::Node::Atom(atom) => &atom.text,
+        katex::Node::MathOrd(ord) => &ord.text,
+        katex::Node::TextOrd(ord) => &ord.text,
+        _ => return None,
+    };
+    let text = match (text.as_str(), left) {
+        ("|" | "∣" | "\\vert", true) => "\\lvert",
+        ("|" | "∣" | "\\vert", false) => "\\rvert",
+        ("‖" | "∥" | "\\|" | "\\Vert", true) => "\\lVert",
+        ("‖" | "∥" | "\\|" | "\\Vert", false) => "\\rVert",
+        ("⌊", _) => "\\lfloor",
+        ("⌋", _) => "\\rfloor",
+        ("⌈", _) => "\\lceil",
+        ("⌉", _) => "\\rceil",
+        ("⟨", _) => "\\langle",
+        ("⟩", _) => "\\rangle",
+        (text, _) => text,
+    };
+    katex::DELIMITERS.contains(&text).then(|| text.to_string())
+}
+
+/// The KaTeX delimiter for a Typst delimiter character, or the null delimiter
+/// when KaTeX cannot stretch it.
+pub fn fence(c: char, left: bool) -> String {
+    let node = katex::Symbol::get(katex::Mode::Math, c).create_node();
+    delimiter(&node, left).unwrap_or(".".to_string())
+}
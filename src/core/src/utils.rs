@@ -1,16 +1,29 @@
+use std::ops::Range;
+
 use comemo::Prehashed;
 use comemo::Track;
 use typst;
 use typst::World;
+use typst::foundations::Content;
+use typst::syntax::{FileId, Source, Span, VirtualPath};
+
+use crate::katex;
+use crate::error::{ConvertError, ConvertResult};
 
+/// A world without files, fonts or packages, in which expressions are evaluated.
+/// Expressions reaching for them fail with an error rather than aborting.
 pub struct FakeWorld {
     library: Prehashed<typst::Library>,
+    book: Prehashed<typst::text::FontBook>,
+    main: Source,
 }
 
 impl FakeWorld {
     pub fn new() -> Self {
         FakeWorld {
             library: Prehashed::new(typst::Library::build()),
+            book: Prehashed::new(typst::text::FontBook::new()),
+            main: Source::detached(""),
         }
     }
 }
@@ -20,59 +33,104 @@ impl World for FakeWorld {
         &self.library
     }
     fn book(&self) ->  &Prehashed<typst::text::FontBook> {
-        unimplemented!();
+        &self.book
     }
     fn file(&self,id:typst_syntax::FileId) -> typst::diag::FileResult<typst::foundations::Bytes> {
-        unimplemented!();
+        Err(typst::diag::FileError::NotFound(id.vpath().as_rootless_path().into()))
     }
     fn font(&self,index:usize) -> Option<typst::text::Font> {
-        unimplemented!();
+        None
     }
     fn main(&self) -> typst_syntax::Source {
-        unimplemented!();
+        self.main.clone()
     }
     fn packages(&self) ->  &[(typst_syntax::PackageSpec,Option<typst::diag::EcoString>)] {
-        unimplemented!();
+        &[]
     }
     fn source(&self,id:typst_syntax::FileId) -> typst::diag::FileResult<typst_syntax::Source> {
-        unimplemented!();
+        Err(typst::diag::FileError::NotFound(id.vpath().as_rootless_path().into()))
     }
     fn today(&self,offset:Option<i64>) -> Option<typst::foundations::Datetime> {
-        unimplemented!();
+        None
     }
 }
 
-pub fn eval(world: &dyn typst::World, string: &str) -> Result<typst::foundations::Content, String> {
-    // Make engine
-    let introspector = typst::introspection::Introspector::default();
-    let mut locator = typst::introspection::Locator::default();
-    let mut tracer = typst::eval::Tracer::default();
+/// Evaluates a math expression.
+///
+/// The expression is evaluated as an inline equation in a source file of its own,
+/// so that the resulting content carries spans which can be mapped back onto the
+/// expression with [`expression_range`]. The expression is trimmed first, since
+/// surrounding whitespace would make the equation a block, and the closing `$`
+/// goes on a line of its own, so that a trailing comment does not hide it. The
+/// display mode is thus left to the settings.
+pub fn eval(world: &dyn typst::World, string: &str) -> ConvertResult<(Content, Source)> {
+    let id = FileId::new(None, VirtualPath::new("main.typ"));
+    let source = Source::new(id, format!("${}\n$", string.trim()));
 
-    let engine = typst::engine::Engine {
-        world: world.track(),
-        introspector: introspector.track(),
-        route: typst::engine::Route::default(),
-        locator: &mut locator,
-        tracer: tracer.track_mut(),
-    };
+    let route = typst::engine::Route::default();
+    let mut tracer = typst::eval::Tracer::default();
 
-    let result = typst::eval::eval_string(
+    let result = typst::eval::eval(
         world.track(),
-        string,
-        typst::syntax::Span::detached(),
-        typst::eval::EvalMode::Math,
-        world.library().math.scope().clone()
+        route.track(),
+        tracer.track_mut(),
+        &source,
     );
 
     match result {
-        Ok(value) => match value {
-            typst::foundations::Value::Content(content) => Ok(content),
-            _ => Err("Expected content result.".to_string()),
+        Ok(module) => Ok((module.content(), source)),
+        Err(err) => Err(ConvertError::Eval(err[0].message.to_string()))
+    }
+}
+
+/// The length of the whitespace [`eval`] trims off the start of an expression.
+pub fn trim_offset(string: &str) -> usize {
+    string.len() - string.trim_start().len()
+}
+
+/// The byte range of a span within the expression passed to [`eval`], given
+/// the [`trim_offset`] of the expression.
+pub fn expression_range(source: &Source, span: Span, offset: usize) -> Option<Range<usize>> {
+    let range = source.range(span)?;
+    let len = source.text().len() - 3;
+    let start = range.start.saturating_sub(1).min(len) + offset;
+    let end = range.end.saturating_sub(1).min(len) + offset;
+    Some(start..end)
+}
+
+/// Converts a length into a KaTeX measurement, in the unit it was most likely
+/// written in. Typst points are PostScript points, which KaTeX calls `bp`, and
+/// lengths mixing em and absolute parts are converted to em with the font size.
+pub fn measurement(length: typst::layout::Length, font_size: typst::layout::Abs) -> katex::Measurement {
+    let (number, unit) = if length.abs.is_zero() {
+        let em = length.em.get();
+        if !is_round(em, 2) && is_round(em * 18.0, 0) {
+            (em * 18.0, "mu")
+        } else {
+            (em, "em")
         }
-        Err(err) => Err(err[0].message.to_string())
+    } else if length.em.is_zero() {
+        let abs = length.abs;
+        [(abs.to_inches(), "in"), (abs.to_cm(), "cm"), (abs.to_mm(), "mm")]
+            .into_iter()
+            .find(|&(number, _)| is_round(number, 2))
+            .unwrap_or((abs.to_pt(), "bp"))
+    } else {
+        (length.em.get() + length.abs / font_size, "em")
+    };
+
+    katex::Measurement {
+        number: number as f32,
+        unit: unit.to_string(),
     }
 }
 
+/// Whether the number has no more than the given number of decimal digits.
+fn is_round(number: f64, digits: i32) -> bool {
+    let scaled = number * 10f64.powi(digits);
+    (scaled - scaled.round()).abs() < 1e-6
+}
+
 pub fn insert_separator<T: Clone>(list: &[T], separator: T) -> Vec<T> {
     list.iter()
         .flat_map(|x| vec![x.clone(), separator.clone()])
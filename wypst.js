@@ -1,17 +1,25 @@
-import init, { parseTree as _parseTree, typstContentTree } from './src/core/pkg';
+import init, { parseTree as _parseTree, parseTreeWithDiagnostics as _parseTreeWithDiagnostics, resolveReference, typstContentTree } from './src/core/pkg';
 import utils from './src/utils';
 
 import wasm from './src/core/pkg/core_bg.wasm';
 
 function parseTree(expression, settings) {
-    expression = expression.trim().replace(/\n/g, ' ');
+    // Newlines are replaced one for one, so that source locations still
+    // point into the expression. It is trimmed by the parser.
+    expression = expression.replace(/\n/g, ' ');
     return _parseTree(expression, settings);
 }
 
+function parseTreeWithDiagnostics(expression, settings) {
+    expression = expression.replace(/\n/g, ' ');
+    return _parseTreeWithDiagnostics(expression, settings);
+}
+
 function renderToDomTree(expression, options) {
     let settings = new utils.Settings(options);
     try {
-        const tree = parseTree(expression, settings);
+        // KaTeX settings only keep KaTeX options, so the others are passed as given.
+        const tree = parseTree(expression, Object.assign({}, options, settings));
         return utils.buildTree(tree, expression, settings);
     } catch (error) {
         // Temporary fix so that we actually see errors like "unknown variable: ..."
@@ -53,6 +61,8 @@ export default {
     render,
     renderToString,
     parseTree,
+    parseTreeWithDiagnostics,
+    resolveReference,
     __typstContentTree: typstContentTree,
     initialize,
 };
@@ -61,5 +71,7 @@ export {
     render,
     renderToString,
     parseTree,
+    parseTreeWithDiagnostics,
+    resolveReference,
     initialize,
 };